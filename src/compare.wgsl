@@ -0,0 +1,33 @@
+#include "quad.wgsl"
+#include "compare_uniform.wgsl"
+
+@group(0) @binding(0)
+var<uniform> compare: Compare;
+
+@group(1) @binding(0)
+var current_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var current_sampler: sampler;
+@group(1) @binding(2)
+var compared_texture: texture_2d<f32>;
+@group(1) @binding(3)
+var compared_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size: vec2<f32> = vec2<f32>(textureDimensions(current_texture));
+    // both were drawn at the size of the target, so pixels line up
+    let uv: vec2<f32> = in.clip_position.xy / size;
+    let current: vec4<f32> = textureSample(current_texture, current_sampler, uv);
+    let compared: vec4<f32> = textureSample(compared_texture, compared_sampler, uv);
+
+    if (compare.difference != 0u) {
+        let difference: vec3<f32> = abs(current.rgb - compared.rgb) * compare.gain;
+        return vec4<f32>(min(difference, vec3<f32>(1.0)), 1.0);
+    }
+    let divider: f32 = compare.divider * size.x;
+    if (abs(in.clip_position.x - divider) < compare.line_width * 0.5) {
+        return vec4<f32>(1.0);
+    }
+    return select(compared, current, in.clip_position.x < divider);
+}
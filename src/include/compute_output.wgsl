@@ -0,0 +1,5 @@
+// results of the compute pass, see `ComputeDescriptor`
+@group(2) @binding(1)
+var compute_texture: texture_2d<f32>;
+@group(2) @binding(2)
+var compute_sampler: sampler;
@@ -0,0 +1,25 @@
+fn base_hash(p: vec2<u32>) -> u32 {
+	let temp32: vec2<u32> = 1103515245u * ((p >> vec2<u32>(1u)) ^ p.yx);
+	let hash32: u32 = 1103515245u * (temp32.x ^ (temp32.y >> 3u));
+	return hash32 ^ (hash32 >> 16u);
+}
+
+fn hash1(seed: ptr<function, f32>) -> f32 {
+    let x: f32 = (*seed) + 0.1; (*seed) = x + 0.1;
+	let n: u32 = base_hash(bitcast<vec2<u32>>(vec2<f32>(x, (*seed))));
+	return f32(n) / f32(0xffffffffu);
+}
+
+fn hash2(seed: ptr<function, f32>) -> vec2<f32> {
+    let x: f32 = (*seed) + 0.1; (*seed) = x + 0.1;
+	let n: u32 = base_hash(bitcast<vec2<u32>>(vec2<f32>(x, (*seed))));
+	let rz: vec2<u32> = vec2<u32>(n, n * 48271u);
+	return vec2<f32>(rz.xy & vec2<u32>(0x7fffffffu)) / f32(0x7fffffffu);
+}
+
+fn hash3(seed: ptr<function, f32>) -> vec3<f32> {
+    let x: f32 = (*seed) + 0.1; (*seed) = x + 0.1;
+    let n: u32 = base_hash(bitcast<vec2<u32>>(vec2<f32>(x, (*seed))));
+	let rz: vec3<u32> = vec3<u32>(n, n * 16807u, n * 48271u);
+	return vec3<f32>(rz & vec3<u32>(0x7fffffffu)) / f32(0x7fffffffu);
+}
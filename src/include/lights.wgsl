@@ -0,0 +1,7 @@
+#include "light.wgsl"
+
+// as many lights as MAX_LIGHTS in uniform/light.rs
+struct Lights {
+    lights: array<Light, 4>,
+    count: u32,
+}
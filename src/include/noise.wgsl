@@ -0,0 +1,35 @@
+#include "hash.wgsl"
+
+#ifndef NOISE_OCTAVES
+#define NOISE_OCTAVES 5
+#endif
+
+// hash of an integer lattice point, in [0, 1]
+fn lattice_hash(p: vec2<f32>) -> f32 {
+    return f32(base_hash(bitcast<vec2<u32>>(vec2<i32>(p)))) / f32(0xffffffffu);
+}
+
+// smooth value noise, in [0, 1]
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i: vec2<f32> = floor(p);
+    let f: vec2<f32> = fract(p);
+    let u: vec2<f32> = f * f * (3.0 - 2.0 * f);
+    let a: f32 = lattice_hash(i);
+    let b: f32 = lattice_hash(i + vec2<f32>(1.0, 0.0));
+    let c: f32 = lattice_hash(i + vec2<f32>(0.0, 1.0));
+    let d: f32 = lattice_hash(i + vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// fractal brownian motion over NOISE_OCTAVES octaves of value noise
+fn fbm(p: vec2<f32>) -> f32 {
+    var value: f32 = 0.0;
+    var amplitude: f32 = 0.5;
+    var q: vec2<f32> = p;
+    for (var i: i32 = 0; i < NOISE_OCTAVES; i = i + 1) {
+        value = value + amplitude * value_noise(q);
+        q = q * 2.0;
+        amplitude = amplitude * 0.5;
+    }
+    return value;
+}
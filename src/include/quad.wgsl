@@ -0,0 +1,19 @@
+// Vertex shader
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.uv = model.uv;
+    // on the far plane, so anything drawn over the background passes the depth test
+    out.clip_position = vec4<f32>(model.position.xy, 1.0, 1.0);
+    return out;
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform System {
+    float time;
+    uint frame;
+    uvec2 resolution;
+    vec4 mouse;
+} system;
+
+#define iTime system.time
+#define iFrame int(system.frame)
+#define iResolution vec3(vec2(system.resolution), 1.0)
+#define iMouse system.mouse
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord);
+
+void main() {
+    // Shadertoy puts the origin in the bottom left corner
+    vec2 fragCoord = vec2(gl_FragCoord.x, iResolution.y - gl_FragCoord.y);
+    mainImage(f_color, fragCoord);
+}
@@ -0,0 +1,7 @@
+#include "system_uniform.wgsl"
+@group(0) @binding(0)
+var<uniform> system: System;
+
+#include "camera_uniform.wgsl"
+@group(1) @binding(0)
+var<uniform> camera: Camera;
@@ -1,90 +1,394 @@
+//! Shadertoy-style fragment shaders, compute passes and particles drawn with wgpu.
+//!
+//! [`run`] and [`run_with`] open a window and drive everything themselves, [`run_windows`] opens
+//! several drawing with one device. To embed the renderer in an application of your own, create a
+//! [`Renderer`] on your window with [`Renderer::builder`] and call [`Renderer::update`] and
+//! [`Renderer::render`] every frame. To draw into textures of a device you already own, create a
+//! [`Scene`] with [`RendererBuilder::build_scene`] instead.
+//!
+//! Shaders, textures, models, lights and parameters can be described together in a [`SceneFile`]
+//! and handed to [`RendererBuilder::scene_file`].
+
+#![warn(missing_docs)]
+
 mod state;
 
-use self::state::State;
+pub use self::state::{
+    CacheStats,
+    Renderer,
+    builder::RendererBuilder,
+    camera::CameraSettings,
+    compare::CompareMode,
+    compute::ComputeDescriptor,
+    config::{FormatPreference, RendererConfig},
+    error::InitError,
+    scene::Scene,
+    scene_file::{SceneFile, SceneFormat},
+    screenshot::Screenshot,
+    headless::HeadlessRenderer,
+    geometry::{MeshBuilder, VertexBinding, quad::{DrawQuad, QuadVertex}},
+    shader::{ShaderCode, library::ShaderProgram},
+    texture::{Texture, TextureBuilder},
+    uniform::{Flush, Uniform, UniformBinding, bundle::{BindGroupBundle, BundleBuilder}, dynamic::DynamicUniformBuffer},
+    window::{FullscreenMode, WindowOptions}
+};
+pub use wgpu;
+pub use winit;
+use self::state::{gpu::Gpu, time::Instant, window::WindowChrome};
+use std::{path::PathBuf, rc::Rc};
 use winit::{
     event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder
+    event_loop::{ControlFlow, EventLoopBuilder},
+    window::Window
 };
 use winit::event::DeviceEvent::MouseMotion;
 #[cfg(target_arch="wasm32")]
+use std::cell::RefCell;
+#[cfg(target_arch="wasm32")]
+use winit::{dpi::PhysicalSize, event_loop::EventLoopProxy};
+#[cfg(target_arch="wasm32")]
 use wasm_bindgen::prelude::*;
 
+/// Requests sent to the running event loop from outside of it.
+#[derive(Debug)]
+enum UserEvent {
+    #[cfg_attr(not(target_arch="wasm32"), allow(dead_code))]
+    SelectShader(String),
+    /// A screenshot of the first window, at the given or its usual scale.
+    #[cfg_attr(not(target_arch="wasm32"), allow(dead_code))]
+    Screenshot(Option<u32>),
+    /// A new connection to the GPU after the device was lost.
+    DeviceRecovered(Result<Gpu, InitError>),
+}
+
+#[cfg(target_arch="wasm32")]
+thread_local! {
+    static EVENT_LOOP_PROXY: RefCell<Option<EventLoopProxy<UserEvent>>> = RefCell::new(None);
+}
+
+/// Switches the running renderer to the bundled shader called `name`.
+#[cfg(target_arch="wasm32")]
+#[wasm_bindgen]
+pub fn set_shader(name: String) {
+    EVENT_LOOP_PROXY.with(|proxy| {
+        if let Some(proxy) = proxy.borrow().as_ref() {
+            let _ = proxy.send_event(UserEvent::SelectShader(name));
+        }
+    });
+}
+
+/// Downloads the current frame as a PNG, `scale` times the canvas size or as large when omitted.
+#[cfg(target_arch="wasm32")]
+#[wasm_bindgen]
+pub fn screenshot(scale: Option<u32>) {
+    EVENT_LOOP_PROXY.with(|proxy| {
+        if let Some(proxy) = proxy.borrow().as_ref() {
+            let _ = proxy.send_event(UserEvent::Screenshot(scale));
+        }
+    });
+}
+
+/// Lists the names accepted by [`set_shader`].
+#[cfg(target_arch="wasm32")]
+#[wasm_bindgen]
+pub fn shader_names() -> js_sys::Array {
+    ShaderProgram::bundled_names().map(JsValue::from).collect()
+}
+
+/// Opens a window drawing the default shader, and on the web starts as soon as the module loads.
 #[cfg_attr(target_arch="wasm32", wasm_bindgen(start))]
 pub async fn run() {
+    // failures are already shown on the page
+    let _ = run_with(Renderer::builder(), WindowOptions::default()).await;
+}
+
+// logs why the renderer couldn't start and, on the web, replaces the canvas with the message
+fn report_init_error(error: &InitError) {
+    log::error!("{}", error);
+
+    #[cfg(target_arch = "wasm32")]
+    web_sys::window().and_then(|win| win.document()).and_then(|doc| {
+        let dst = doc.get_element_by_id("container")?;
+        let message = doc.create_element("p").ok()?;
+        message.set_class_name("error");
+        message.set_text_content(Some(&error.to_string()));
+        dst.set_inner_html("");
+        dst.append_child(&message).ok()?;
+        Some(())
+    });
+}
+
+/// Opens a window and runs the renderer `builder` describes in it.
+///
+/// Only returns when the renderer couldn't start, after reporting why.
+pub async fn run_with(builder: RendererBuilder, options: WindowOptions) -> Result<(), InitError> {
+    run_windows(vec![(builder, options)]).await
+}
+
+// a window and the renderer presenting to it, dropped in that order
+struct RendererWindow {
+    renderer: Renderer,
+    chrome: WindowChrome,
+    window: Rc<Window>,
+    last_render_time: Instant,
+    screenshot_scale: u32,
+    #[cfg_attr(target_arch="wasm32", allow(dead_code))]
+    screenshot_dir: PathBuf,
+    // waiting for the GPU natively, browsers resolve them on their own
+    #[cfg_attr(target_arch="wasm32", allow(dead_code))]
+    screenshots: Vec<Screenshot>,
+}
+
+impl RendererWindow {
+    // draws the frame again, to be saved or downloaded once it's read back
+    fn screenshot(&mut self, scale: u32) {
+        let screenshot = match self.renderer.screenshot(scale) {
+            Ok(screenshot) => screenshot,
+            Err(error) => {
+                log::error!("Couldn't take a screenshot: {:#}", error);
+                return;
+            }
+        };
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(async move {
+            let file_name = screenshot.file_name();
+            if let Err(error) = screenshot.await.and_then(|image| state::screenshot::download(&image, &file_name)) {
+                log::error!("Couldn't download the screenshot: {:#}", error);
+            }
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        self.screenshots.push(screenshot);
+    }
+
+    // saves the screenshots the GPU is done with, `Maintain::Wait` waits for all of them
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_screenshots(&mut self, maintain: wgpu::Maintain) {
+        if self.screenshots.is_empty() {
+            return;
+        }
+        self.renderer.device().poll(maintain);
+        let directory = &self.screenshot_dir;
+        self.screenshots.retain(|screenshot| match screenshot.try_take() {
+            None => true,
+            Some(Ok(image)) => {
+                state::screenshot::save(image, directory.join(screenshot.file_name()));
+                false
+            }
+            Some(Err(error)) => {
+                log::error!("Couldn't read the screenshot back: {:#}", error);
+                false
+            }
+        });
+    }
+}
+
+/// Opens a window for every renderer in `windows`, each with its own shader and camera, and runs
+/// them until the last one is closed. They share the device, queue and pipelines of the first.
+///
+/// Window events go to the window they happened in, mouse motion to the focused one.
+/// Only returns when a renderer couldn't start, after reporting why.
+pub async fn run_windows(windows: Vec<(RendererBuilder, WindowOptions)>) -> Result<(), InitError> {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             std::panic::set_hook(Box::new(console_error_panic_hook::hook));
             console_log::init_with_level(log::Level::Warn).expect("Couldn't initialize logger");
         } else {
-            env_logger::init();
+            // the embedding binary may have set up logging already
+            let _ = env_logger::try_init();
         }
     }
 
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+    let mut opened: Vec<RendererWindow> = Vec::with_capacity(windows.len());
+    for (builder, options) in windows {
+        let (window, chrome) = WindowChrome::build(&options, &event_loop).unwrap();
+        // shared with the task reconnecting to the GPU after a device loss
+        let window = Rc::new(window);
 
-    #[cfg(target_arch = "wasm32")]
-    {
-        // Winit prevents sizing with CSS, so we have to set the size manually when on web.
-        use winit::dpi::PhysicalSize;
-
-        use winit::platform::web::WindowExtWebSys;
-        web_sys::window().and_then(|win| win.document()).and_then(|doc| {
-            let dst = doc.get_element_by_id("container")?;
-            window.set_inner_size(PhysicalSize::new(dst.client_width(), dst.client_height()));
-            let canvas = web_sys::Element::from(window.canvas());
-            dst.append_child(&canvas).ok()?;
-            Some(())
-        }).expect("Couldn't append canvas to document body.");
+        #[cfg(target_arch = "wasm32")]
+        {
+            // Winit prevents sizing with CSS, so we have to set the size manually when on web.
+            use winit::platform::web::WindowExtWebSys;
+            web_sys::window().and_then(|win| win.document()).and_then(|doc| {
+                let dst = doc.get_element_by_id("container")?;
+                window.set_inner_size(PhysicalSize::new(dst.client_width(), dst.client_height()));
+                let canvas = web_sys::Element::from(window.canvas());
+                dst.append_child(&canvas).ok()?;
+                Some(())
+            }).expect("Couldn't append canvas to document body.");
+        }
+
+        let size = window.inner_size();
+        // the window is moved into the event loop along with the renderer, so it lives as long
+        let renderer = match opened.first() {
+            Some(first) => unsafe { builder.build_sharing(&*window, size.width, size.height, &first.renderer) },
+            None => unsafe { builder.build(&*window, size.width, size.height) }.await,
+        };
+        match renderer {
+            Ok(renderer) => opened.push(RendererWindow {
+                renderer,
+                chrome,
+                window,
+                last_render_time: Instant::now(),
+                screenshot_scale: options.screenshot_scale,
+                screenshot_dir: options.screenshot_dir.clone(),
+                screenshots: Vec::new(),
+            }),
+            Err(error) => {
+                report_init_error(&error);
+                return Err(error);
+            }
+        }
     }
+    let mut windows = opened;
+    let proxy = event_loop.create_proxy();
+    // the window whose renderer is reconnecting to the GPU, the others follow once it's done
+    let mut recovering = None;
+    let mut focused = None;
 
-    // State::new uses async code, so we're going to wait for it to finish
-    let mut state = State::new(include_str!("./default.wgsl"), &window).await;
+    #[cfg(target_arch = "wasm32")]
+    EVENT_LOOP_PROXY.with(|proxy| *proxy.borrow_mut() = Some(event_loop.create_proxy()));
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
         match event {
-            Event::MainEventsCleared => window.request_redraw(),
+            Event::MainEventsCleared => {
+                #[cfg(not(target_arch = "wasm32"))]
+                windows.iter_mut().for_each(|entry| entry.save_screenshots(wgpu::Maintain::Poll));
+                windows.iter().for_each(|entry| entry.window.request_redraw());
+            }
             Event::DeviceEvent {
                 event: MouseMotion{ delta },
                 .. // We're not using device_id currently
-            } => state.handle_mouse_input(delta.0, delta.1),
+            } => {
+                let index = windows.iter().position(|entry| Some(entry.window.id()) == focused).unwrap_or(0);
+                if let Some(entry) = windows.get_mut(index) {
+                    entry.renderer.handle_mouse_input(delta.0, delta.1);
+                }
+            }
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == window.id() => {
-                if !state.handle_input(event) {
-                    match event {
-                        #[cfg(not(target_arch="wasm32"))]
-                        WindowEvent::CloseRequested |
-                        WindowEvent::KeyboardInput {
-                            input: KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            },
+            } => {
+                let Some(index) = windows.iter().position(|entry| entry.window.id() == window_id) else {
+                    return;
+                };
+                let entry = &mut windows[index];
+                if entry.renderer.handle_input(event) {
+                    return;
+                }
+                match event {
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::CloseRequested |
+                    WindowEvent::KeyboardInput {
+                        input: KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Escape),
                             ..
-                        } => *control_flow = ControlFlow::Exit,
-                        WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
-                        }
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            state.resize(**new_inner_size);
+                        },
+                        ..
+                    } => {
+                        let mut entry = windows.remove(index);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        entry.save_screenshots(wgpu::Maintain::Wait);
+                        entry.chrome.save(&entry.window);
+                        if windows.is_empty() {
+                            *control_flow = ControlFlow::Exit;
                         }
-                        _ => {}
+                    }
+                    WindowEvent::KeyboardInput {
+                        input: KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F11),
+                            ..
+                        },
+                        ..
+                    } => entry.chrome.toggle_fullscreen(&entry.window),
+                    WindowEvent::KeyboardInput {
+                        input: KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F2),
+                            ..
+                        },
+                        ..
+                    } => entry.screenshot(entry.screenshot_scale),
+                    WindowEvent::Focused(true) => focused = Some(window_id),
+                    WindowEvent::Moved(position) => entry.chrome.moved(&entry.window, *position),
+                    WindowEvent::Resized(physical_size) => {
+                        entry.chrome.resized(&entry.window, *physical_size);
+                        entry.renderer.resize(physical_size.width, physical_size.height);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        entry.renderer.resize(new_inner_size.width, new_inner_size.height);
+                    }
+                    _ => {}
+                }
+            }
+            Event::UserEvent(UserEvent::SelectShader(name)) => {
+                if let Some(entry) = windows.first_mut() {
+                    if let Err(error) = entry.renderer.select_shader(&name) {
+                        log::error!("Couldn't switch to shader `{}`: {:#}", name, error);
                     }
                 }
             }
-            Event::RedrawRequested(window_id) if window_id == window.id() => {
-                state.update();
-                match state.render() {
-                    Ok(_) => {}
-                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => state.resize(state.current_size()),
+            Event::UserEvent(UserEvent::Screenshot(scale)) => {
+                if let Some(entry) = windows.first_mut() {
+                    entry.screenshot(scale.unwrap_or(entry.screenshot_scale));
+                }
+            }
+            Event::UserEvent(UserEvent::DeviceRecovered(gpu)) => {
+                let Some(index) = recovering.take().and_then(|id| windows.iter().position(|entry| entry.window.id() == id)) else {
+                    return;
+                };
+                let mut recovered = windows.remove(index);
+                let result = gpu.and_then(|gpu| recovered.renderer.rebuild(gpu)).and_then(|()| {
+                    windows.iter_mut().try_for_each(|entry| unsafe { entry.renderer.recover_sharing(&*entry.window, &recovered.renderer) })
+                });
+                windows.insert(index, recovered);
+                if let Err(error) = result {
+                    report_init_error(&error);
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            Event::RedrawRequested(window_id) => {
+                let Some(entry) = windows.iter_mut().find(|entry| entry.window.id() == window_id) else {
+                    return;
+                };
+                // the device is shared, so nothing renders until one window has replaced it
+                if entry.renderer.device_lost() {
+                    if recovering.is_none() {
+                        recovering = Some(window_id);
+                        // the window can't have a second surface while the old one lives
+                        entry.renderer.release_surface();
+                        let (config, window, proxy) = (entry.renderer.config().clone(), Rc::clone(&entry.window), proxy.clone());
+                        let reconnect = async move {
+                            let _ = proxy.send_event(UserEvent::DeviceRecovered(unsafe { Gpu::new(&config, &*window) }.await));
+                        };
+                        #[cfg(target_arch = "wasm32")]
+                        wasm_bindgen_futures::spawn_local(reconnect);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        pollster::block_on(reconnect);
+                    }
+                    return;
+                }
+                let now = Instant::now();
+                entry.renderer.update(now - entry.last_render_time);
+                entry.last_render_time = now;
+                match entry.renderer.render() {
+                    Ok(_) => entry.chrome.frame(&entry.window, entry.renderer.current_shader(), entry.renderer.pipeline_stats()),
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        let (width, height) = entry.renderer.size();
+                        entry.renderer.resize(width, height);
+                    }
                     Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                     Err(wgpu::SurfaceError::Timeout) => log::warn!("Surface timeout"),
                 }
             }
+            Event::LoopDestroyed => windows.iter_mut().for_each(|entry| {
+                #[cfg(not(target_arch = "wasm32"))]
+                entry.save_screenshots(wgpu::Maintain::Wait);
+                entry.chrome.save(&entry.window);
+            }),
             _ => {}
         }
     });
@@ -0,0 +1,9 @@
+#include "uniforms.wgsl"
+#include "quad.wgsl"
+#include "compute_output.wgsl"
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let cell: vec4<f32> = textureSample(compute_texture, compute_sampler, in.uv);
+    return vec4<f32>(cell.rgb * vec3<f32>(0.4, 0.9, 0.6), 1.0);
+}
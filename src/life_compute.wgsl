@@ -0,0 +1,47 @@
+#include "uniforms.wgsl"
+#include "hash.wgsl"
+
+#define GRID 256u
+
+// two generations of cells, the current one is picked by frame parity
+@group(2) @binding(0)
+var<storage, read_write> cells: array<u32>;
+@group(2) @binding(1)
+var compute_output: texture_storage_2d<rgba16float, write>;
+
+fn cell_index(x: i32, y: i32, generation: u32) -> u32 {
+    let wrapped_x: u32 = u32((x + i32(GRID)) % i32(GRID));
+    let wrapped_y: u32 = u32((y + i32(GRID)) % i32(GRID));
+    return generation * GRID * GRID + wrapped_y * GRID + wrapped_x;
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= GRID || id.y >= GRID) {
+        return;
+    }
+    let x: i32 = i32(id.x);
+    let y: i32 = i32(id.y);
+    let current: u32 = system.frame % 2u;
+    let next: u32 = 1u - current;
+
+    var alive: u32 = 0u;
+    if (system.frame == 0u) {
+        var seed: f32 = f32(id.x) + f32(id.y) * f32(GRID) + system.time;
+        alive = select(0u, 1u, hash1(&seed) > 0.7);
+    } else {
+        var neighbours: u32 = 0u;
+        for (var dy: i32 = -1; dy <= 1; dy = dy + 1) {
+            for (var dx: i32 = -1; dx <= 1; dx = dx + 1) {
+                if (dx != 0 || dy != 0) {
+                    neighbours = neighbours + cells[cell_index(x + dx, y + dy, current)];
+                }
+            }
+        }
+        let was_alive: bool = cells[cell_index(x, y, current)] == 1u;
+        alive = select(0u, 1u, neighbours == 3u || (was_alive && neighbours == 2u));
+    }
+
+    cells[cell_index(x, y, next)] = alive;
+    textureStore(compute_output, vec2<i32>(x, y), vec4<f32>(vec3<f32>(f32(alive)), 1.0));
+}
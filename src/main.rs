@@ -1,5 +1,441 @@
-use hello_world::run;
+use std::{path::{Path, PathBuf}, process::ExitCode, time::Duration};
+use clap::{Args, Parser, Subcommand, ValueEnum, builder::{PossibleValuesParser, TypedValueParser}, error::ErrorKind};
+use hello_world::{
+    run_windows, CompareMode, FullscreenMode, HeadlessRenderer, Renderer, RendererBuilder, RendererConfig, SceneFile, ShaderCode, ShaderProgram, WindowOptions,
+    wgpu::{Backends, PresentMode}
+};
 
-fn main() {
-    pollster::block_on(run());
-}
\ No newline at end of file
+// exit codes scripts can tell apart, clap exits with 2 on bad arguments
+const EXIT_INIT: u8 = 1;
+const EXIT_INVALID: u8 = 3;
+const EXIT_IO: u8 = 4;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  the renderer couldn't start, e.g. no adapter or the shader didn't compile
+  2  invalid arguments
+  3  a shader or scene file failed validation
+  4  a file couldn't be read or written";
+
+/// Renders Shadertoy-style fragment shaders, compute passes and particles with wgpu.
+///
+/// Without a subcommand, opens a window. Tab cycles shaders, P toggles particles,
+/// V cycles present modes, F2 saves a screenshot, F11 toggles fullscreen and WASD, Space, Shift and the mouse move the camera.
+/// C compares with the next shader, B cycles the compared one and X switches between the split,
+/// whose divider can be dragged, and the difference of the two. L draws models as wireframes.
+#[derive(Debug, Parser)]
+#[command(version, after_help = EXIT_CODES)]
+struct Cli {
+    /// Bundled shader name, shader of the scene or path to a .wgsl, .glsl, .frag or .spv file
+    shader: Option<String>,
+
+    #[command(flatten)]
+    scene: SceneArgs,
+
+    /// Window size in physical pixels, as WIDTHxHEIGHT
+    #[arg(long, value_parser = parse_size)]
+    size: Option<(u32, u32)>,
+
+    /// Open the window fullscreen, F11 toggles it
+    #[arg(long)]
+    fullscreen: bool,
+
+    /// Make fullscreen exclusive, in the monitor's largest video mode, instead of borderless
+    #[arg(long)]
+    exclusive: bool,
+
+    /// Index of the monitor to open the window on
+    #[arg(long)]
+    monitor: Option<usize>,
+
+    /// Neither restore nor save where the window was in the previous run
+    #[arg(long)]
+    forget_window: bool,
+
+    /// Directory F2 saves screenshots to
+    #[arg(long, default_value = ".")]
+    screenshot_dir: PathBuf,
+
+    /// Take screenshots at this many times the window size
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=8))]
+    screenshot_scale: u32,
+
+    /// Open another window drawing SHADER on the same device, may be repeated
+    #[arg(long = "window", value_name = "SHADER")]
+    windows: Vec<String>,
+
+    /// Presentation mode, falls back to fifo when unsupported
+    #[arg(long, value_enum, default_value_t = PresentModeArg::Fifo)]
+    present_mode: PresentModeArg,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Args)]
+struct SceneArgs {
+    /// TOML, JSON or RON scene file with shaders, channels, models, lights and parameters
+    #[arg(long, global = true)]
+    scene: Option<PathBuf>,
+
+    /// RON file of particle emitters to simulate from the start
+    #[arg(long, global = true)]
+    particles: Option<PathBuf>,
+
+    /// Graphics backend to use
+    #[arg(long, value_enum, global = true, default_value_t = BackendArg::All)]
+    backend: BackendArg,
+
+    /// Multisampling sample count, 1 disables it
+    #[arg(long, global = true, default_value = "1",
+          value_parser = PossibleValuesParser::new(["1", "2", "4", "8"]).map(|count| count.parse::<u32>().unwrap()))]
+    msaa: u32,
+
+    /// Initial camera pose as X,Y,Z or X,Y,Z,YAW,PITCH with angles in degrees
+    #[arg(long, global = true, value_parser = parse_camera, allow_hyphen_values = true)]
+    camera: Option<([f32; 3], f32, f32)>,
+
+    /// Seconds the shader clock starts at
+    #[arg(long, global = true, default_value = "0", value_parser = parse_time)]
+    time: Duration,
+
+    /// Bundled shader name, shader of the scene or shader file to draw right of the current one
+    #[arg(long, global = true, value_name = "SHADER")]
+    compare: Option<String>,
+
+    /// Show the difference between the compared shaders instead of splitting the frame
+    #[arg(long, global = true, requires = "compare")]
+    difference: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Render a frame without a window and write it as PNG
+    Render {
+        /// PNG file to write
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Image size in pixels, as WIDTHxHEIGHT
+        #[arg(long, value_parser = parse_size, default_value = "1280x720")]
+        size: (u32, u32),
+        /// Frames simulated at 60 fps up to the one written, which is drawn at --time. Fewer when they'd start before 0
+        #[arg(long, default_value_t = 1)]
+        frames: u32,
+    },
+    /// Render a sequence of frames without a window and write them as numbered PNGs
+    Export {
+        /// Directory the frames are written to, created when missing
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Image size in pixels, as WIDTHxHEIGHT
+        #[arg(long, value_parser = parse_size, default_value = "1280x720")]
+        size: (u32, u32),
+        /// Frames per second of shader time
+        #[arg(long, default_value_t = 30.0, value_parser = parse_positive)]
+        fps: f64,
+        /// Seconds of shader time to export, starting at --time
+        #[arg(long, default_value_t = 5.0, value_parser = parse_positive)]
+        duration: f64,
+    },
+    /// Check that shaders compile and match the bindings the renderer provides, without a GPU
+    Validate {
+        /// Bundled shader names or shader files, every bundled shader when none are given
+        shaders: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PresentModeArg {
+    Fifo,
+    Mailbox,
+    Immediate,
+    AutoVsync,
+    AutoNoVsync,
+}
+
+impl From<PresentModeArg> for PresentMode {
+    fn from(mode: PresentModeArg) -> Self {
+        match mode {
+            PresentModeArg::Fifo => Self::Fifo,
+            PresentModeArg::Mailbox => Self::Mailbox,
+            PresentModeArg::Immediate => Self::Immediate,
+            PresentModeArg::AutoVsync => Self::AutoVsync,
+            PresentModeArg::AutoNoVsync => Self::AutoNoVsync,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BackendArg {
+    All,
+    Primary,
+    Vulkan,
+    Metal,
+    Dx12,
+    Dx11,
+    Gl,
+}
+
+impl From<BackendArg> for Backends {
+    fn from(backend: BackendArg) -> Self {
+        match backend {
+            BackendArg::All => Self::all(),
+            BackendArg::Primary => Self::PRIMARY,
+            BackendArg::Vulkan => Self::VULKAN,
+            BackendArg::Metal => Self::METAL,
+            BackendArg::Dx12 => Self::DX12,
+            BackendArg::Dx11 => Self::DX11,
+            BackendArg::Gl => Self::GL,
+        }
+    }
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let parse = |value: &str| value.trim().parse::<u32>().ok().filter(|value| *value > 0);
+    match size.split_once(['x', 'X']) {
+        Some((width, height)) => parse(width).zip(parse(height)),
+        None => None,
+    }.ok_or_else(|| format!("`{}` isn't WIDTHxHEIGHT with positive integers, e.g. 1280x720", size))
+}
+
+fn parse_time(time: &str) -> Result<Duration, String> {
+    let seconds = time.trim().parse::<f64>().map_err(|error| format!("`{}`: {}", time, error))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("`{}` isn't a non-negative number of seconds a clock can hold", time))
+}
+
+fn parse_positive(value: &str) -> Result<f64, String> {
+    value.trim().parse::<f64>().ok().filter(|value| value.is_finite() && *value > 0.0)
+        .ok_or_else(|| format!("`{}` isn't a positive number", value))
+}
+
+// exits like clap does on arguments that only conflict once parsed
+fn invalid_arguments(message: String) -> ! {
+    clap::Error::raw(ErrorKind::ValueValidation, format!("{}\n", message)).exit()
+}
+
+fn parse_camera(pose: &str) -> Result<([f32; 3], f32, f32), String> {
+    let values = pose.split(',').map(|value| value.trim().parse::<f32>()).collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("`{}`: {}", pose, error))?;
+    match values[..] {
+        [x, y, z] => Ok(([x, y, z], -90.0, -20.0)),
+        [x, y, z, yaw, pitch] => Ok(([x, y, z], yaw, pitch)),
+        _ => Err(format!("`{}` isn't X,Y,Z or X,Y,Z,YAW,PITCH", pose)),
+    }
+}
+
+// the shader as a bundled program, or read from the file it names
+fn load_program(shader: &str) -> Result<ShaderProgram, String> {
+    if let Some(program) = ShaderProgram::bundled(shader) {
+        return Ok(program);
+    }
+    ShaderCode::from_file(Path::new(shader)).map(ShaderProgram::new).map_err(|error| {
+        let bundled: Vec<_> = ShaderProgram::bundled_names().collect();
+        format!("`{}` is neither a bundled shader ({}) nor a readable shader file: {:#}", shader, bundled.join(", "), error)
+    })
+}
+
+// the window geometry in the platform's configuration directory
+fn geometry_file() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("entrance").join("window.ron"))
+}
+
+// the builder falls back to the default shader with a warning, scripts and typos want to know
+fn check_shader(scene: Option<&SceneFile>, shader: &str) -> Result<(), ExitCode> {
+    let declared = scene.is_some_and(|scene| scene.shader_names().any(|name| name == shader));
+    if let (false, Err(error)) = (declared, load_program(shader)) {
+        log::error!("{}", error);
+        return Err(ExitCode::from(EXIT_IO));
+    }
+    Ok(())
+}
+
+fn load_scene(cli: &Cli) -> Result<Option<SceneFile>, ExitCode> {
+    let Some(path) = &cli.scene.scene else {
+        return Ok(None);
+    };
+    SceneFile::load(path).map(Some).map_err(|error| {
+        log::error!("{:#}", error);
+        ExitCode::from(EXIT_INVALID)
+    })
+}
+
+fn builder(cli: &Cli, scene: Option<SceneFile>) -> Result<RendererBuilder, ExitCode> {
+    let mut builder = Renderer::builder()
+        .config(RendererConfig {
+            present_mode: cli.present_mode.into(),
+            msaa_samples: cli.scene.msaa,
+            backends: cli.scene.backend.into(),
+            ..RendererConfig::default()
+        })
+        .time(cli.scene.time);
+    if let Some(scene) = scene {
+        builder = builder.scene_file(scene);
+    }
+    // a shader named on the command line wins over the scene's
+    if let Some(shader) = &cli.shader {
+        builder = builder.shader(shader);
+    }
+    if let Some((position, yaw, pitch)) = cli.scene.camera {
+        builder = builder.camera(position, yaw, pitch);
+    }
+    if let Some(shader) = &cli.scene.compare {
+        let mode = if cli.scene.difference { CompareMode::Difference } else { CompareMode::Split };
+        builder = builder.compare(shader, mode);
+    }
+    if let Some(path) = &cli.scene.particles {
+        match std::fs::read_to_string(path) {
+            Ok(particles) => builder = builder.particles(&particles),
+            Err(error) => {
+                log::error!("Couldn't read `{}`: {}", path.display(), error);
+                return Err(ExitCode::from(EXIT_IO));
+            }
+        }
+    }
+    Ok(builder)
+}
+
+// the offscreen renderer drawing the shader, with the clock at `time`
+fn headless(cli: &Cli, (width, height): (u32, u32), time: Duration) -> Result<HeadlessRenderer, ExitCode> {
+    let scene = load_scene(cli)?;
+    for shader in cli.shader.iter().chain(&cli.scene.compare) {
+        check_shader(scene.as_ref(), shader)?;
+    }
+    let builder = builder(cli, scene)?.time(time);
+    pollster::block_on(builder.build_headless(width, height)).map_err(|error| {
+        log::error!("{}", error);
+        ExitCode::from(EXIT_INIT)
+    })
+}
+
+// the main window, then one for every --window, which only the main one's geometry is remembered for
+fn windows(cli: &Cli) -> Result<Vec<(RendererBuilder, WindowOptions)>, ExitCode> {
+    let scene = load_scene(cli)?;
+    for shader in cli.shader.iter().chain(&cli.scene.compare).chain(&cli.windows) {
+        check_shader(scene.as_ref(), shader)?;
+    }
+    let options = WindowOptions {
+        title: "entrance".to_string(),
+        size: cli.size,
+        fullscreen: cli.fullscreen,
+        fullscreen_mode: if cli.exclusive { FullscreenMode::Exclusive } else { FullscreenMode::Borderless },
+        monitor: cli.monitor,
+        geometry_file: (!cli.forget_window).then(geometry_file).flatten(),
+        screenshot_scale: cli.screenshot_scale,
+        screenshot_dir: cli.screenshot_dir.clone(),
+    };
+    let mut windows = vec![(builder(cli, scene.clone())?, options.clone())];
+    for shader in &cli.windows {
+        let options = WindowOptions { fullscreen: false, geometry_file: None, ..options.clone() };
+        windows.push((builder(cli, scene.clone())?.shader(shader), options));
+    }
+    Ok(windows)
+}
+
+fn save(renderer: &HeadlessRenderer, path: &Path) -> Result<(), ExitCode> {
+    let image = pollster::block_on(renderer.read_pixels()).map_err(|error| {
+        log::error!("Couldn't read the frame back: {:#}", error);
+        ExitCode::from(EXIT_INIT)
+    })?;
+    image.save(path).map_err(|error| {
+        log::error!("Couldn't write `{}`: {}", path.display(), error);
+        ExitCode::from(EXIT_IO)
+    })
+}
+
+fn render(cli: &Cli, output: &Path, size: (u32, u32), frames: u32) -> Result<(), ExitCode> {
+    let step = Duration::from_secs_f64(1.0 / 60.0);
+    // the earlier frames lead up to the requested time, as many as fit after 0
+    let fitting = u32::try_from(cli.scene.time.as_nanos() / step.as_nanos()).unwrap_or(u32::MAX).saturating_add(1);
+    if frames > fitting {
+        log::info!("{} frames at 60 fps would start before 0, simulating {}", frames, fitting);
+    }
+    let frames = frames.clamp(1, fitting);
+    let mut renderer = headless(cli, size, cli.scene.time - step * (frames - 1))?;
+    for frame in 0..frames {
+        renderer.update(if frame == 0 { Duration::ZERO } else { step });
+        renderer.render();
+    }
+    save(&renderer, output)?;
+    log::info!("Wrote `{}`", output.display());
+    Ok(())
+}
+
+fn export(cli: &Cli, output: &Path, size: (u32, u32), fps: f64, duration: f64) -> Result<(), ExitCode> {
+    if let Err(error) = std::fs::create_dir_all(output) {
+        log::error!("Couldn't create `{}`: {}", output.display(), error);
+        return Err(ExitCode::from(EXIT_IO));
+    }
+    // both are positive, but together they may still not make a sequence
+    let Ok(step) = Duration::try_from_secs_f64(1.0 / fps) else {
+        invalid_arguments(format!("--fps {} is too low to step the clock by", fps));
+    };
+    let frames = (duration * fps).ceil();
+    if frames > f64::from(u32::MAX) {
+        invalid_arguments(format!("--duration {} at --fps {} is more frames than can be numbered", duration, fps));
+    }
+    let frames = (frames as u32).max(1);
+    let mut renderer = headless(cli, size, cli.scene.time)?;
+    for frame in 0..frames {
+        renderer.update(if frame == 0 { Duration::ZERO } else { step });
+        renderer.render();
+        save(&renderer, &output.join(format!("frame_{:05}.png", frame)))?;
+    }
+    log::info!("Wrote {} frames to `{}`", frames, output.display());
+    Ok(())
+}
+
+fn validate(cli: &Cli, shaders: &[String]) -> Result<(), ExitCode> {
+    // loading the scene already validated the shaders it declares
+    let scene = load_scene(cli)?;
+    let declared: Vec<String> = scene.iter().flat_map(|scene| scene.shader_names().map(String::from)).collect();
+    let shaders: Vec<String> = match shaders {
+        [] => ShaderProgram::bundled_names().map(String::from).chain(declared.iter().cloned()).collect(),
+        shaders => shaders.to_vec(),
+    };
+    let mut code = None;
+    for shader in &shaders {
+        if declared.contains(shader) {
+            println!("ok      {}", shader);
+            continue;
+        }
+        match load_program(shader) {
+            Ok(program) => match program.validate() {
+                Ok(()) => println!("ok      {}", shader),
+                Err(error) => {
+                    println!("invalid {}\n{:#}", shader, error);
+                    code.get_or_insert(EXIT_INVALID);
+                }
+            },
+            Err(error) => {
+                println!("missing {}\n{}", shader, error);
+                code = Some(EXIT_IO);
+            }
+        }
+    }
+    code.map_or(Ok(()), |code| Err(ExitCode::from(code)))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // progress of the subcommands is worth seeing, the window only logs problems by default
+    let level = if cli.command.is_some() { "warn,entrance=info,hello_world=info" } else { "error" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).init();
+
+    let result = match &cli.command {
+        None => windows(&cli).and_then(|windows| {
+            // the error has already been logged
+            pollster::block_on(run_windows(windows)).map_err(|_| ExitCode::from(EXIT_INIT))
+        }),
+        Some(Command::Render { output, size, frames }) => render(&cli, output, *size, *frames),
+        Some(Command::Export { output, size, fps, duration }) => export(&cli, output, *size, *fps, *duration),
+        Some(Command::Validate { shaders }) => validate(&cli, shaders),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(code) => code,
+    }
+}
@@ -0,0 +1,69 @@
+#include "camera_uniform.wgsl"
+#include "lights.wgsl"
+
+@group(0) @binding(0)
+var diffuse_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var diffuse_sampler: sampler;
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(2) @binding(0)
+var<uniform> lights: Lights;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+}
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) world_normal: vec3<f32>,
+}
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let world_position: vec4<f32> = model * vec4<f32>(vertex.position, 1.0);
+
+    var out: VertexOutput;
+    out.uv = vertex.uv;
+    out.world_position = world_position.xyz;
+    // placements scale uniformly, so the model matrix transforms normals as well
+    out.world_normal = (model * vec4<f32>(vertex.normal, 0.0)).xyz;
+    out.clip_position = camera.view_proj * world_position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let base: vec4<f32> = textureSample(diffuse_texture, diffuse_sampler, in.uv);
+    let normal: vec3<f32> = normalize(in.world_normal);
+    let view_dir: vec3<f32> = normalize(camera.view_pos.xyz - in.world_position);
+
+    var color: vec3<f32> = base.rgb * 0.05;
+    for (var i: u32 = 0u; i < lights.count; i = i + 1u) {
+        let light: Light = lights.lights[i];
+        let to_light: vec3<f32> = light.position - in.world_position;
+        let light_dir: vec3<f32> = normalize(to_light);
+        let half_dir: vec3<f32> = normalize(view_dir + light_dir);
+        let falloff: f32 = clamp(1.0 - length(to_light) / light.range, 0.0, 1.0);
+        let radiance: vec3<f32> = light.color * light.intensity * falloff * falloff;
+
+        let diffuse: f32 = max(dot(normal, light_dir), 0.0);
+        let specular: f32 = pow(max(dot(normal, half_dir), 0.0), 32.0);
+        color = color + radiance * (base.rgb * diffuse + vec3<f32>(specular * 0.5));
+    }
+    return vec4<f32>(color, base.a);
+}
@@ -0,0 +1,41 @@
+#include "uniforms.wgsl"
+#include "particle.wgsl"
+
+@group(2) @binding(0)
+var<storage, read> particles: array<Particle>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput, @builtin(instance_index) instance: u32) -> VertexOutput {
+    let particle: Particle = particles[instance];
+    // dead particles collapse to a point and are never rasterized
+    let size: f32 = select(0.0, particle.size, particle.age < particle.lifetime);
+    let aspect: f32 = f32(system.resolution.y) / f32(system.resolution.x);
+
+    var out: VertexOutput;
+    out.uv = model.uv;
+    out.color = particle.color;
+    // offset in clip space keeps the billboard facing the camera and shrinking with distance
+    out.clip_position = camera.view_proj * vec4<f32>(particle.position, 1.0)
+        + vec4<f32>(model.position.x * size * aspect, model.position.y * size, 0.0, 0.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance: f32 = length(in.uv * 2.0 - 1.0);
+    if (distance > 1.0) {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb * (1.0 - distance * distance), in.color.a);
+}
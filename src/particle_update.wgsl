@@ -0,0 +1,51 @@
+#include "hash.wgsl"
+#include "particle.wgsl"
+#include "particle_emitter.wgsl"
+#include "particle_simulation.wgsl"
+
+@group(0) @binding(0)
+var<uniform> simulation: Simulation;
+@group(0) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2)
+var<storage, read> emitters: array<Emitter>;
+
+fn respawn(particle: ptr<function, Particle>, emitter: Emitter, index: u32) {
+    var seed: f32 = f32(index) * 0.618 + simulation.time;
+    let offset: vec3<f32> = hash3(&seed) * 2.0 - 1.0;
+    let jitter: vec2<f32> = hash2(&seed);
+
+    let first_spawn: bool = (*particle).lifetime == 0.0;
+    (*particle).position = emitter.position;
+    (*particle).velocity = normalize(emitter.direction + offset * emitter.spread) * emitter.speed * (0.75 + 0.5 * jitter.x);
+    (*particle).lifetime = emitter.lifetime * (0.5 + 0.5 * jitter.y);
+    // stagger the first generation so the emitter starts in its steady state
+    (*particle).age = select(0.0, jitter.x * (*particle).lifetime, first_spawn);
+    (*particle).color = emitter.color;
+    (*particle).size = emitter.size;
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index: u32 = id.x;
+    if (index >= simulation.particle_count) {
+        return;
+    }
+
+    var particle: Particle = particles[index];
+    for (var i: u32 = 0u; i < simulation.emitter_count; i = i + 1u) {
+        let emitter: Emitter = emitters[i];
+        if (index >= emitter.first && index < emitter.first + emitter.count) {
+            particle.age = particle.age + simulation.delta_time;
+            if (particle.age >= particle.lifetime) {
+                respawn(&particle, emitter, index);
+            } else {
+                particle.velocity = (particle.velocity + simulation.gravity * simulation.delta_time)
+                    * max(1.0 - simulation.drag * simulation.delta_time, 0.0);
+                particle.position = particle.position + particle.velocity * simulation.delta_time;
+            }
+            break;
+        }
+    }
+    particles[index] = particle;
+}
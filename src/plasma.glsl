@@ -0,0 +1,10 @@
+// Shadertoy-style plasma, compiled through the GLSL prelude
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec2 mouse = iMouse.xy / iResolution.xy;
+    float v = sin(uv.x * 10.0 + iTime)
+            + sin((uv.y * 10.0 + iTime) * 0.5)
+            + sin(length(uv - mouse) * 12.0 - iTime * 2.0);
+    vec3 color = 0.5 + 0.5 * cos(v + vec3(0.0, 2.0, 4.0));
+    fragColor = vec4(color, 1.0);
+}
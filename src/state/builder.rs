@@ -0,0 +1,207 @@
+use std::rc::Rc;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use wgpu::{Adapter, Device, Queue, TextureFormat};
+use winit::dpi::PhysicalSize;
+use super::{
+    Renderer,
+    camera::CameraSettings,
+    compare::CompareMode,
+    config::RendererConfig,
+    error::InitError,
+    gpu::{self, Capabilities, Gpu},
+    headless::HeadlessRenderer,
+    pipeline::RenderTarget,
+    scene::{Scene, SceneContent},
+    scene_file::SceneFile,
+    texture::Texture,
+    time::Duration,
+    shader::library::{ShaderLibrary, ShaderProgram}
+};
+
+/// Settings a [`Renderer`] starts with, see [`Renderer::builder`].
+#[derive(Debug, Clone)]
+pub struct RendererBuilder {
+    config: RendererConfig,
+    shader: String,
+    particles: Option<String>,
+    shaders: Vec<(String, ShaderProgram)>,
+    camera: CameraSettings,
+    time: Duration,
+    content: SceneContent,
+    compare: Option<(String, CompareMode)>,
+}
+
+impl Default for RendererBuilder {
+    fn default() -> Self {
+        Self {
+            config: RendererConfig::default(),
+            shader: "default".to_string(),
+            particles: None,
+            shaders: Vec::new(),
+            camera: CameraSettings::default(),
+            time: Duration::ZERO,
+            content: SceneContent::default(),
+            compare: None,
+        }
+    }
+}
+
+impl RendererBuilder {
+    /// Adapter, surface and multisampling settings, see [`RendererConfig`].
+    pub fn config(mut self, config: RendererConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Name of the shader to start with, or natively the path of a shader file to load.
+    pub fn shader(mut self, shader: &str) -> Self {
+        self.shader = shader.to_string();
+        self
+    }
+
+    /// RON emitter definitions simulated from the start, otherwise toggling particles shows the bundled fountain.
+    pub fn particles(mut self, definitions: &str) -> Self {
+        self.particles = Some(definitions.to_string());
+        self
+    }
+
+    /// Registers `program` next to the bundled shaders, replacing any called the same.
+    pub fn add_shader(mut self, name: &str, program: ShaderProgram) -> Self {
+        self.shaders.push((name.to_string(), program));
+        self
+    }
+
+    /// Starts the camera at `position`, looking along `yaw` and `pitch` in degrees.
+    pub fn camera(mut self, position: [f32; 3], yaw: f32, pitch: f32) -> Self {
+        self.camera = CameraSettings { position, yaw, pitch, ..self.camera };
+        self
+    }
+
+    /// Sets the camera's starting pose, lens and controls at once.
+    pub fn camera_settings(mut self, settings: CameraSettings) -> Self {
+        self.camera = settings;
+        self
+    }
+
+    /// Color the target is cleared to before the shader draws.
+    pub fn clear_color(mut self, color: [f32; 4]) -> Self {
+        self.content.clear_color = color;
+        self
+    }
+
+    /// Takes the shaders, camera, models, lights and parameters of a scene file, replacing
+    /// what was set before. The scene's shader and particles are only taken when it names them.
+    pub fn scene_file(mut self, file: SceneFile) -> Self {
+        if let Some(shader) = file.shader {
+            self.shader = shader;
+        }
+        if file.particles.is_some() {
+            self.particles = file.particles;
+        }
+        self.shaders.extend(file.shaders);
+        self.camera = file.camera;
+        self.content = file.content;
+        self
+    }
+
+    /// Starts the clock shaders see at `time` instead of zero.
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Starts comparing the shader with the one called `name`, see [`Scene::compare`]. Natively, `name` may also be a shader file.
+    pub fn compare(mut self, name: &str, mode: CompareMode) -> Self {
+        self.compare = Some((name.to_string(), mode));
+        self
+    }
+
+    // the pose, time and comparison to start with
+    fn prepare(&self, device: &Device, queue: &Queue, scene: &mut Scene) -> Result<(), InitError> {
+        scene.set_camera_settings(&self.camera);
+        scene.set_time(self.time);
+        if let Some((name, mode)) = &self.compare {
+            scene.set_compare_mode(*mode);
+            scene.compare(device, queue, name)
+                .map_err(|error| InitError::Shader(error.context(format!("compared shader `{}`", name))))?;
+        }
+        Ok(())
+    }
+
+    fn library(&mut self) -> (ShaderLibrary, String) {
+        let mut library = ShaderLibrary::bundled();
+        for (name, program) in self.shaders.drain(..) {
+            library.insert(&name, program);
+        }
+        let name = Renderer::initial_shader(&mut library, &self.shader);
+        // the compared shader may be a file as well
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((compared, _)) = &mut self.compare {
+            if library.get(compared).is_none() && std::path::Path::new(compared).is_file() {
+                match library.load(std::path::Path::new(compared)) {
+                    Ok(loaded) => *compared = loaded,
+                    Err(error) => log::error!("Couldn't load shader `{}`: {:#}", compared, error),
+                }
+            }
+        }
+        (library, name)
+    }
+
+    /// Connects to the GPU and creates a renderer presenting to `window`, whose inner size is `width` by `height`.
+    ///
+    /// # Safety
+    /// `window` must outlive the renderer.
+    pub async unsafe fn build<W: HasRawWindowHandle + HasRawDisplayHandle>(self, window: &W, width: u32, height: u32) -> Result<Renderer, InitError> {
+        let gpu = Gpu::new(&self.config, window).await?;
+        self.finish(gpu, width, height)
+    }
+
+    /// Creates a renderer presenting to another window with the device, queue and pipelines of `other`.
+    ///
+    /// Only the surface settings of the config apply, the device is the one `other` was built with.
+    ///
+    /// # Safety
+    /// `window` must outlive the renderer.
+    pub unsafe fn build_sharing<W: HasRawWindowHandle + HasRawDisplayHandle>(self, window: &W, width: u32, height: u32,
+                                                                             other: &Renderer) -> Result<Renderer, InitError> {
+        let gpu = Gpu::share(other.gpu(), window)?;
+        self.finish(gpu, width, height)
+    }
+
+    fn finish(mut self, gpu: Gpu, width: u32, height: u32) -> Result<Renderer, InitError> {
+        let (library, name) = self.library();
+        let content = std::mem::take(&mut self.content);
+        let mut renderer = Renderer::with_gpu(gpu, self.config.clone(), library, &name, self.particles.as_deref(), content,
+                                              PhysicalSize::new(width, height))?;
+        let context = Rc::clone(renderer.gpu());
+        self.prepare(&context.device, &context.queue, renderer.scene_mut())?;
+        Ok(renderer)
+    }
+
+    /// Connects to any adapter, software ones included, and creates a renderer drawing `width` by `height`
+    /// frames in [`HeadlessRenderer::FORMAT`] without a window.
+    pub async fn build_headless(self, width: u32, height: u32) -> Result<HeadlessRenderer, InitError> {
+        let (adapter, device, queue) = gpu::request_headless(&self.config).await?;
+        let scene = self.build_scene(&adapter, &device, &queue, HeadlessRenderer::FORMAT, width, height)?;
+        Ok(HeadlessRenderer::new(device, queue, scene))
+    }
+
+    /// Creates a scene on a device owned elsewhere, drawing into `width` by `height` targets of `format`.
+    /// `adapter` is the one `device` was requested from, it tells which shaders the scene can run.
+    ///
+    /// Only `msaa_samples` of the config applies, clamped to what `adapter` supports for `format`.
+    pub fn build_scene(mut self, adapter: &Adapter, device: &Device, queue: &Queue, format: TextureFormat,
+                       width: u32, height: u32) -> Result<Scene, InitError> {
+        let (library, name) = self.library();
+        let target = RenderTarget {
+            color_format: format,
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count: self.config.sample_count(adapter, format),
+        };
+        let content = std::mem::take(&mut self.content);
+        let mut scene = Scene::new(device, queue, Capabilities::new(adapter), Rc::default(), library, &name, self.particles.as_deref(),
+                                   content, target, PhysicalSize::new(width, height))?;
+        self.prepare(device, queue, &mut scene)?;
+        Ok(scene)
+    }
+}
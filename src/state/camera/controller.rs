@@ -5,7 +5,7 @@ use winit::event::{ElementState, MouseScrollDelta, VirtualKeyCode};
 use crate::state::camera::Camera;
 use crate::state::time::Duration;
 
-const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
+pub(super) const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
 
 #[derive(Debug)]
 pub(in crate::state) struct CameraController {
@@ -1,6 +1,10 @@
 pub(super) mod controller;
+#[cfg(test)]
+mod tests;
 
-use cgmath::{InnerSpace, Matrix4, perspective, Point3, Rad, Vector3};
+use cgmath::{Deg, InnerSpace, Matrix4, perspective, Point3, Rad, Vector3};
+use serde::Deserialize;
+use self::controller::CameraController;
 
 #[rustfmt::skip]
 pub(super) const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
@@ -10,6 +14,54 @@ pub(super) const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
+/// Starting pose, lens and controls of the camera, in degrees where angles are involved.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraSettings {
+    /// Where the camera starts, in world space.
+    pub position: [f32; 3],
+    /// Turn around the y axis, 0 looks along x.
+    pub yaw: f32,
+    /// Tilt up from the horizon.
+    pub pitch: f32,
+    /// Vertical field of view.
+    pub fov: f32,
+    /// Distance of the near clipping plane.
+    pub near: f32,
+    /// Distance of the far clipping plane.
+    pub far: f32,
+    /// Units per second moved with the keys.
+    pub speed: f32,
+    /// How fast the mouse turns the camera.
+    pub sensitivity: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 5.0, 10.0],
+            yaw: -90.0,
+            pitch: -20.0,
+            fov: 45.0,
+            near: 0.1,
+            far: 100.0,
+            speed: 4.0,
+            sensitivity: 0.4,
+        }
+    }
+}
+
+impl CameraSettings {
+    /// The camera, its projection onto a `width` by `height` target and its controller.
+    pub(super) fn create(&self, width: u32, height: u32) -> (Camera, Projection, CameraController) {
+        (
+            Camera::new(self.position, Deg(self.yaw), Deg(self.pitch)),
+            Projection::new(width, height, Deg(self.fov), self.near, self.far),
+            CameraController::new(self.speed, self.sensitivity),
+        )
+    }
+}
+
 #[derive(Debug)]
 pub(super) struct Camera {
     pub(super) position: Point3<f32>,
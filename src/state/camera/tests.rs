@@ -0,0 +1,210 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+use cgmath::{Deg, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3, Vector4};
+use proptest::prelude::*;
+use winit::event::{ElementState, MouseScrollDelta, VirtualKeyCode};
+use super::{
+    Camera, Projection, OPENGL_TO_WGPU_MATRIX,
+    controller::{CameraController, SAFE_FRAC_PI_2}
+};
+use crate::state::time::Duration;
+
+const SECOND: Duration = Duration::from_secs(1);
+
+fn assert_close(actual: Vector3<f32>, expected: Vector3<f32>) {
+    assert!((actual - expected).magnitude() < 1e-4, "{:?} isn't {:?}", actual, expected);
+}
+
+// where `point` ends up in normalized device coordinates
+fn project(matrix: Matrix4<f32>, point: Vector3<f32>) -> Vector3<f32> {
+    let clip = matrix * point.extend(1.0);
+    clip.truncate() / clip.w
+}
+
+// how far `matrix` times its inverse strays from the identity, in its largest entry
+fn inversion_error(matrix: Matrix4<f32>) -> Option<f32> {
+    let inverse = matrix.invert()?;
+    let product: [[f32; 4]; 4] = (matrix * inverse).into();
+    let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+    Some(product.iter().flatten().zip(identity.iter().flatten()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max))
+}
+
+// moves `camera` like holding `key` for a second does
+fn hold(camera: &mut Camera, key: VirtualKeyCode, speed: f32) {
+    let mut controller = CameraController::new(speed, 1.0);
+    controller.process_keyboard(key, ElementState::Pressed);
+    controller.update_camera(camera, SECOND);
+}
+
+#[test]
+fn opengl_depth_is_remapped_to_zero_to_one() {
+    assert_eq!(OPENGL_TO_WGPU_MATRIX * Vector4::new(0.3, -0.7, -1.0, 1.0), Vector4::new(0.3, -0.7, 0.0, 1.0));
+    assert_eq!(OPENGL_TO_WGPU_MATRIX * Vector4::new(0.3, -0.7, 1.0, 1.0), Vector4::new(0.3, -0.7, 1.0, 1.0));
+    assert_eq!(OPENGL_TO_WGPU_MATRIX * Vector4::new(0.0, 0.0, 0.0, 1.0), Vector4::new(0.0, 0.0, 0.5, 1.0));
+}
+
+#[test]
+fn near_and_far_planes_map_to_zero_and_one() {
+    let projection = Projection::new(1280, 720, Deg(45.0), 0.1, 100.0);
+    let matrix = projection.calc_matrix();
+    assert!(project(matrix, Vector3::new(0.0, 0.0, -0.1)).z.abs() < 1e-5);
+    assert!((project(matrix, Vector3::new(0.0, 0.0, -100.0)).z - 1.0).abs() < 1e-5);
+    // in between, farther is deeper
+    let depths: Vec<f32> = [0.5_f32, 1.0, 10.0, 50.0].iter().map(|distance| project(matrix, Vector3::new(0.0, 0.0, -distance)).z).collect();
+    assert!(depths.windows(2).all(|pair| 0.0 < pair[0] && pair[0] < pair[1] && pair[1] < 1.0), "{:?}", depths);
+}
+
+#[test]
+fn resizing_keeps_the_vertical_field_of_view() {
+    let mut projection = Projection::new(800, 800, Deg(90.0), 0.1, 100.0);
+    // at 90°, the top of the view is as far up as the point is away
+    assert!((project(projection.calc_matrix(), Vector3::new(0.0, 2.0, -2.0)).y - 1.0).abs() < 1e-5);
+    projection.resize(1600, 800);
+    assert!((project(projection.calc_matrix(), Vector3::new(0.0, 2.0, -2.0)).y - 1.0).abs() < 1e-5);
+    assert!((project(projection.calc_matrix(), Vector3::new(4.0, 0.0, -2.0)).x - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn camera_looks_along_yaw_and_pitch() {
+    for (yaw, pitch, direction) in [
+        (0.0, 0.0, Vector3::unit_x()),
+        (90.0, 0.0, Vector3::unit_z()),
+        (-90.0, 0.0, -Vector3::unit_z()),
+        (180.0, 45.0, Vector3::new(-1.0, 1.0, 0.0).normalize()),
+    ] {
+        let camera = Camera::new((1.0, 2.0, 3.0), Deg(yaw), Deg(pitch));
+        let view = camera.calc_matrix();
+        // what the camera looks at is straight ahead, down the negative z axis of view space
+        let ahead = view * (camera.position + direction).to_homogeneous();
+        assert_close(ahead.truncate(), -Vector3::unit_z());
+        assert_close((view * camera.position.to_homogeneous()).truncate(), Vector3::new(0.0, 0.0, 0.0));
+    }
+}
+
+#[test]
+fn keys_move_relative_to_yaw() {
+    for yaw in [0.0_f32, 90.0, -90.0, 30.0, 200.0] {
+        let (sin, cos) = yaw.to_radians().sin_cos();
+        let forward = Vector3::new(cos, 0.0, sin);
+        let right = Vector3::new(-sin, 0.0, cos);
+        for (key, direction) in [
+            (VirtualKeyCode::W, forward),
+            (VirtualKeyCode::S, -forward),
+            (VirtualKeyCode::D, right),
+            (VirtualKeyCode::A, -right),
+            (VirtualKeyCode::Space, Vector3::unit_y()),
+            (VirtualKeyCode::LShift, -Vector3::unit_y()),
+        ] {
+            // pitch doesn't tilt walking
+            let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(yaw), Deg(-30.0));
+            hold(&mut camera, key, 2.0);
+            assert_close(camera.position - Point3::new(0.0, 0.0, 0.0), direction * 2.0);
+        }
+    }
+}
+
+#[test]
+fn released_keys_stop_moving() {
+    let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let mut controller = CameraController::new(1.0, 1.0);
+    controller.process_keyboard(VirtualKeyCode::W, ElementState::Pressed);
+    controller.process_keyboard(VirtualKeyCode::W, ElementState::Released);
+    controller.update_camera(&mut camera, SECOND);
+    assert_eq!(camera.position, Point3::new(0.0, 0.0, 0.0));
+    assert!(!controller.process_keyboard(VirtualKeyCode::Q, ElementState::Pressed));
+}
+
+#[test]
+fn pitch_is_clamped_short_of_straight_up_and_down() {
+    let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let mut controller = CameraController::new(1.0, 1.0);
+    // moving the mouse up looks up
+    controller.process_mouse(0.0, -10.0);
+    controller.update_camera(&mut camera, SECOND);
+    assert_eq!(camera.pitch, Rad(SAFE_FRAC_PI_2));
+    controller.process_mouse(0.0, 10.0);
+    controller.update_camera(&mut camera, SECOND);
+    assert_eq!(camera.pitch, -Rad(SAFE_FRAC_PI_2));
+    const { assert!(SAFE_FRAC_PI_2 < FRAC_PI_2) };
+    // looking almost straight down still has a view
+    assert!(inversion_error(camera.calc_matrix()).is_some_and(|error| error < 1e-3));
+}
+
+#[test]
+fn mouse_motion_is_used_once() {
+    let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let mut controller = CameraController::new(1.0, 0.5);
+    controller.process_mouse(0.2, 0.0);
+    controller.update_camera(&mut camera, SECOND);
+    assert!((camera.yaw.0 - 0.1).abs() < 1e-6);
+    controller.update_camera(&mut camera, SECOND);
+    assert!((camera.yaw.0 - 0.1).abs() < 1e-6);
+}
+
+#[test]
+fn scrolling_dollies_along_the_view_direction() {
+    let (yaw, pitch) = (Deg(30.0), Deg(-20.0));
+    let start = Camera::new((1.0, 2.0, 3.0), yaw, pitch).position;
+    let looking = {
+        let (sin_pitch, cos_pitch) = Rad::from(pitch).0.sin_cos();
+        let (sin_yaw, cos_yaw) = Rad::from(yaw).0.sin_cos();
+        Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw)
+    };
+    for (delta, distance) in [
+        // a line is half a unit, scaled by speed and sensitivity; scrolling up pulls back
+        (MouseScrollDelta::LineDelta(0.0, 1.0), -0.5 * 2.0 * 0.5),
+        (MouseScrollDelta::LineDelta(0.0, -2.0), 1.0 * 2.0 * 0.5),
+        (MouseScrollDelta::PixelDelta((0.0, -3.0).into()), 3.0 * 2.0 * 0.5),
+    ] {
+        let mut camera = Camera::new(start, yaw, pitch);
+        let mut controller = CameraController::new(2.0, 0.5);
+        controller.process_scroll(&delta);
+        controller.update_camera(&mut camera, SECOND);
+        assert_close(camera.position - start, looking * distance);
+        // the scroll is consumed by the update it moved the camera in
+        controller.update_camera(&mut camera, SECOND);
+        assert_close(camera.position - start, looking * distance);
+    }
+}
+
+proptest! {
+    #[test]
+    fn views_are_invertible(x in -1e3_f32..1e3, y in -1e3_f32..1e3, z in -1e3_f32..1e3,
+                            yaw in -2.0 * PI..2.0 * PI, pitch in -SAFE_FRAC_PI_2..=SAFE_FRAC_PI_2) {
+        let camera = Camera::new((x, y, z), Rad(yaw), Rad(pitch));
+        let view = camera.calc_matrix();
+        prop_assert!(inversion_error(view).is_some_and(|error| error < 1e-3));
+        // the inverse puts the eye back where the camera is
+        let eye = view.invert().unwrap() * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        prop_assert!((eye.truncate() - Vector3::new(x, y, z)).magnitude() < 1e-3 * (1.0 + Vector3::new(x, y, z).magnitude()));
+    }
+
+    #[test]
+    fn projections_are_invertible_and_keep_depth_in_range(width in 1_u32..4096, height in 1_u32..4096, fov in 1.0_f32..179.0,
+                                                          near in 0.01_f32..10.0, ratio in 2.0_f32..1e4, t in 0.0_f32..=1.0) {
+        let far = near * ratio;
+        let projection = Projection::new(width, height, Deg(fov), near, far);
+        let matrix = projection.calc_matrix();
+        prop_assert!(matrix.invert().is_some());
+        let depth = project(matrix, Vector3::new(0.0, 0.0, -(near + (far - near) * t))).z;
+        prop_assert!((-1e-4..=1.0 + 1e-4).contains(&depth), "depth {}", depth);
+    }
+
+    #[test]
+    fn view_projections_are_invertible(yaw in -2.0 * PI..2.0 * PI, pitch in -SAFE_FRAC_PI_2..=SAFE_FRAC_PI_2,
+                                       fov in 10.0_f32..120.0, aspect in 0.25_f32..4.0) {
+        let camera = Camera::new((0.0, 5.0, 10.0), Rad(yaw), Rad(pitch));
+        let projection = Projection::new((aspect * 1000.0) as u32, 1000, Deg(fov), 0.1, 100.0);
+        prop_assert!((projection.calc_matrix() * camera.calc_matrix()).invert().is_some());
+    }
+
+    #[test]
+    fn pitch_stays_clamped(motions in proptest::collection::vec((-1e3_f64..1e3, -1e3_f64..1e3), 1..20)) {
+        let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut controller = CameraController::new(1.0, 0.4);
+        for (dx, dy) in motions {
+            controller.process_mouse(dx, dy);
+            controller.update_camera(&mut camera, Duration::from_millis(16));
+            prop_assert!(camera.pitch.0.abs() <= SAFE_FRAC_PI_2);
+        }
+    }
+}
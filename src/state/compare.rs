@@ -0,0 +1,75 @@
+use std::{borrow::Cow, rc::Rc};
+use anyhow::Result;
+use wgpu::{BindGroupLayout, Device, PipelineLayoutDescriptor, RenderPipeline, ShaderStages, TextureView};
+use winit::dpi::PhysicalSize;
+use super::{
+    geometry::quad::QuadVertex,
+    pipeline::{create_pipeline, PipelineDescription, RenderTarget, cache::{PipelineCache, RenderPipelineKey}},
+    shader::{compile_shader, ShaderCode, preprocessor::Preprocessor},
+    texture::Texture,
+    uniform::bundle::BindGroupBundle
+};
+
+const COMPARE_SHADER: ShaderCode = ShaderCode::Wgsl(Cow::Borrowed(include_str!("../compare.wgsl")));
+
+/// How a scene shows the shader it compares the current one with, see [`Scene::compare`](crate::Scene::compare).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompareMode {
+    /// The current shader left of a divider, the compared one right of it.
+    #[default]
+    Split,
+    /// The amplified difference of the two, black where they agree.
+    Difference,
+}
+
+/// The frames of the current and compared shader, drawn before the pass combining them samples both.
+pub(in crate::state) struct CompareTargets {
+    current: Texture,
+    compared: Texture,
+    bind_group: BindGroupBundle,
+}
+
+impl CompareTargets {
+    pub(in crate::state) fn new(device: &Device, cache: &mut PipelineCache, target: RenderTarget, size: PhysicalSize<u32>) -> Self {
+        let size = (size.width, size.height);
+        let current = Texture::create_render_target(device, size, target.color_format, "Current Shader Target");
+        let compared = Texture::create_render_target(device, size, target.color_format, "Compared Shader Target");
+        let bind_group = BindGroupBundle::builder("Compare Bind Group")
+            .texture(&current, ShaderStages::FRAGMENT)
+            .texture(&compared, ShaderStages::FRAGMENT)
+            .build(device, cache);
+        Self { current, compared, bind_group }
+    }
+
+    pub(in crate::state) fn current_view(&self) -> &TextureView {
+        &self.current.view
+    }
+
+    pub(in crate::state) fn compared_view(&self) -> &TextureView {
+        &self.compared.view
+    }
+
+    pub(in crate::state) fn bind_group(&self) -> &BindGroupBundle {
+        &self.bind_group
+    }
+}
+
+/// Combines the two frames over the quad. `bind_group_layouts` are those of the compare uniform and [`CompareTargets`].
+pub(in crate::state) fn compare_pipeline(device: &Device, cache: &mut PipelineCache, preprocessor: &Preprocessor,
+                                         bind_group_layouts: [&BindGroupLayout; 2], target: RenderTarget) -> Result<Rc<RenderPipeline>> {
+    let vertex_layouts = [QuadVertex::desc()];
+    let description = PipelineDescription::OPAQUE;
+    let key = RenderPipelineKey::new(COMPARE_SHADER.fingerprint(), &vertex_layouts, &bind_group_layouts, target, description);
+    let layout_entries: Vec<_> = bind_group_layouts.iter().map(|layout| cache.layout_entries(layout).to_vec()).collect();
+    cache.render_pipeline(key, || {
+        let shader = compile_shader(device, preprocessor, &COMPARE_SHADER)?;
+        let groups: Vec<_> = layout_entries.iter().map(Vec::as_slice).collect();
+        shader.reflection().validate(&groups, &vertex_layouts)?;
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Compare Pipeline Layout"),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        Ok(create_pipeline(device, &layout, target, &description, &vertex_layouts, &shader))
+    })
+}
@@ -0,0 +1,194 @@
+use anyhow::{bail, Result};
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    BufferBindingType, BufferDescriptor, BufferUsages, CommandEncoder, ComputePassDescriptor,
+    ComputePipeline, ComputePipelineDescriptor, Device, Extent3d, FilterMode,
+    PipelineLayoutDescriptor, SamplerDescriptor, ShaderStages,
+    StorageTextureAccess, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDescriptor, TextureViewDimension
+};
+use std::{iter, rc::Rc};
+use crate::state::{
+    pipeline::cache::{ComputePipelineKey, PipelineCache},
+    shader::{compile_shader, ShaderCode, preprocessor::Preprocessor},
+    texture::Texture,
+    uniform::bundle::{BindGroupBundle, storage_entry, texture_entries}
+};
+
+/// Format of the storage texture a compute pass writes and the fragment shader samples.
+pub(in crate::state) const OUTPUT_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// CPU-side description of a compute pass run before every render pass.
+///
+/// The compute shader sees the storage buffer at `@group(2) @binding(0)` and the output
+/// texture at `@group(2) @binding(1)`. The fragment shader sees the same buffer read-only
+/// at `@group(2) @binding(0)`, and the output texture and its sampler at bindings 1 and 2.
+#[derive(Debug, Clone)]
+pub struct ComputeDescriptor {
+    /// WGSL with a `cs_main` entry point.
+    pub code: ShaderCode,
+    /// Size of the storage buffer in bytes.
+    pub buffer_size: u64,
+    /// Size of the output texture in texels.
+    pub texture_size: (u32, u32),
+    /// Must match `@workgroup_size` of `cs_main`.
+    pub workgroup_size: (u32, u32),
+}
+
+/// Entries of the group the compute shader writes its results through.
+pub(in crate::state) fn storage_layout_entries() -> [BindGroupLayoutEntry; 2] {
+    [
+        BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::StorageTexture {
+                access: StorageTextureAccess::WriteOnly,
+                format: OUTPUT_FORMAT,
+                view_dimension: TextureViewDimension::D2,
+            },
+            count: None,
+        },
+    ]
+}
+
+/// Entries of the group the fragment shader reads the results from, as [`ComputePass::new`] bundles them.
+pub(in crate::state) fn output_layout_entries() -> Vec<BindGroupLayoutEntry> {
+    iter::once(storage_entry(ShaderStages::FRAGMENT, true))
+        .chain(texture_entries(ShaderStages::FRAGMENT))
+        .enumerate()
+        .map(|(binding, entry)| BindGroupLayoutEntry { binding: binding as u32, ..entry })
+        .collect()
+}
+
+pub(in crate::state) struct ComputePass {
+    pipeline: Rc<ComputePipeline>,
+    compute_bind_group: BindGroup,
+    render_bind_group: BindGroupBundle,
+    workgroups: (u32, u32),
+}
+
+impl ComputePass {
+    /// `uniform_layouts` are the system and camera layouts, bound at groups 0 and 1.
+    pub(in crate::state) fn new(device: &Device, cache: &mut PipelineCache, preprocessor: &Preprocessor,
+                                uniform_layouts: [&BindGroupLayout; 2], desc: &ComputeDescriptor) -> Result<Self> {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Compute Storage Buffer"),
+            size: desc.buffer_size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Compute Output Texture"),
+            size: Extent3d {
+                width: desc.texture_size.0,
+                height: desc.texture_size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: OUTPUT_FORMAT,
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let compute_bind_group_layout = cache.bind_group_layout(device, &BindGroupLayoutDescriptor {
+            label: Some("Compute Storage Bind Group Layout"),
+            entries: &storage_layout_entries(),
+        });
+        let compute_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Compute Storage Bind Group"),
+            layout: &compute_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&view),
+                },
+            ],
+        });
+
+        let output = Texture { texture, view, sampler };
+        let render_bind_group = BindGroupBundle::builder("Compute Output Bind Group")
+            .storage(&buffer, ShaderStages::FRAGMENT, true)
+            .texture(&output, ShaderStages::FRAGMENT)
+            .build(device, cache);
+
+        let [system_layout, camera_layout] = uniform_layouts;
+        let bind_group_layouts = [system_layout, camera_layout, &compute_bind_group_layout];
+        let key = ComputePipelineKey::new(desc.code.fingerprint(), &bind_group_layouts);
+        let layout_entries: Vec<_> = bind_group_layouts.iter().map(|layout| cache.layout_entries(layout).to_vec()).collect();
+        let pipeline = cache.compute_pipeline(key, || {
+            let shader = compile_shader(device, preprocessor, &desc.code)?;
+            let Some(entry_point) = shader.compute_entry() else {
+                bail!("compute shader has no compute entry point");
+            };
+            let groups: Vec<_> = layout_entries.iter().map(Vec::as_slice).collect();
+            shader.reflection().validate(&groups, &[])?;
+            let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Compute Pipeline Layout"),
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+            Ok(device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("Compute Pipeline"),
+                layout: Some(&layout),
+                module: shader.module(),
+                entry_point,
+            }))
+        })?;
+
+        let workgroups = (
+            desc.texture_size.0.div_ceil(desc.workgroup_size.0),
+            desc.texture_size.1.div_ceil(desc.workgroup_size.1),
+        );
+
+        Ok(Self {
+            pipeline,
+            compute_bind_group,
+            render_bind_group,
+            workgroups,
+        })
+    }
+
+    /// Layout of the group the fragment shader reads the results from.
+    pub(in crate::state) fn render_bind_group_layout(&self) -> &BindGroupLayout {
+        self.render_bind_group.layout()
+    }
+
+    pub(in crate::state) fn render_bind_group(&self) -> &BindGroup {
+        self.render_bind_group.bind_group()
+    }
+
+    pub(in crate::state) fn dispatch(&self, encoder: &mut CommandEncoder, uniform_bind_groups: [&BindGroup; 2]) {
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor { label: Some("Compute Pass") });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, uniform_bind_groups[0], &[]);
+        compute_pass.set_bind_group(1, uniform_bind_groups[1], &[]);
+        compute_pass.set_bind_group(2, &self.compute_bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.workgroups.0, self.workgroups.1, 1);
+    }
+}
@@ -0,0 +1,129 @@
+use wgpu::{
+    Adapter, Backends, CompositeAlphaMode, PowerPreference, PresentMode, Surface,
+    TextureFormat, TextureFormatFeatureFlags
+};
+use super::texture::Texture;
+
+/// Kind of surface format to prefer, the first supported format is used when none matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatPreference {
+    /// Colors are written linearly and encoded to sRGB by the hardware.
+    Srgb,
+    /// Colors are written as-is into an 8 bit per channel format.
+    Linear,
+    /// A float or 10 bit format, for displays that can show extended range.
+    Hdr,
+}
+
+impl FormatPreference {
+    fn matches(self, format: TextureFormat) -> bool {
+        match self {
+            Self::Srgb => format.describe().srgb,
+            Self::Linear => !format.describe().srgb && matches!(format, TextureFormat::Bgra8Unorm | TextureFormat::Rgba8Unorm),
+            Self::Hdr => matches!(format, TextureFormat::Rgba16Float | TextureFormat::Rgb10a2Unorm),
+        }
+    }
+}
+
+/// How the renderer sets up its adapter and surface.
+///
+/// `power_preference`, `backends` and `force_fallback_adapter` are used when the device is created, everything else
+/// can be changed on a running renderer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RendererConfig {
+    /// Falls back to [`PresentMode::Fifo`], which every surface supports.
+    pub present_mode: PresentMode,
+    /// Which of the surface's formats to pick.
+    pub format: FormatPreference,
+    /// Falls back to the first mode the surface supports.
+    pub alpha_mode: CompositeAlphaMode,
+    /// 1 disables multisampling, 4 and more use 4 samples, the only count every adapter supports.
+    pub msaa_samples: u32,
+    /// Whether to prefer an integrated or a discrete GPU.
+    pub power_preference: PowerPreference,
+    /// The backends adapters are looked for on.
+    pub backends: Backends,
+    /// Only use a software adapter, whose output doesn't depend on the GPU, e.g. to compare frames in tests.
+    pub force_fallback_adapter: bool,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            present_mode: PresentMode::Fifo,
+            format: FormatPreference::Srgb,
+            alpha_mode: CompositeAlphaMode::Auto,
+            msaa_samples: 1,
+            power_preference: PowerPreference::default(),
+            backends: Backends::all(),
+            force_fallback_adapter: false,
+        }
+    }
+}
+
+/// Surface settings resolved against what the adapter actually supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct SurfaceChoice {
+    pub(super) present_mode: PresentMode,
+    pub(super) format: TextureFormat,
+    pub(super) alpha_mode: CompositeAlphaMode,
+    pub(super) sample_count: u32,
+}
+
+impl RendererConfig {
+    pub(super) fn choose(&self, surface: &Surface, adapter: &Adapter) -> SurfaceChoice {
+        let present_modes = surface.get_supported_present_modes(adapter);
+        let present_mode = match self.present_mode {
+            // the automatic modes pick their own fallback
+            PresentMode::AutoVsync | PresentMode::AutoNoVsync => self.present_mode,
+            mode if present_modes.contains(&mode) => mode,
+            mode => {
+                log::warn!("Present mode {:?} isn't supported, falling back to Fifo", mode);
+                PresentMode::Fifo
+            }
+        };
+
+        let formats = surface.get_supported_formats(adapter);
+        let format = match formats.iter().find(|format| self.format.matches(**format)) {
+            Some(format) => *format,
+            None => {
+                log::warn!("No {:?} surface format among {:?}, using {:?}", self.format, formats, formats[0]);
+                formats[0]
+            }
+        };
+
+        let alpha_modes = surface.get_supported_alpha_modes(adapter);
+        let alpha_mode = match self.alpha_mode {
+            CompositeAlphaMode::Auto => self.alpha_mode,
+            mode if alpha_modes.contains(&mode) => mode,
+            mode => {
+                log::warn!("Alpha mode {:?} isn't supported, falling back to {:?}", mode, alpha_modes[0]);
+                alpha_modes[0]
+            }
+        };
+
+        let sample_count = self.sample_count(adapter, format);
+
+        SurfaceChoice { present_mode, format, alpha_mode, sample_count }
+    }
+
+    pub(super) fn sample_count(&self, adapter: &Adapter, format: TextureFormat) -> u32 {
+        if self.msaa_samples <= 1 {
+            return 1;
+        }
+        let color = adapter.get_texture_format_features(format).flags;
+        let depth = adapter.get_texture_format_features(Texture::DEPTH_FORMAT).flags;
+        if !color.contains(TextureFormatFeatureFlags::MULTISAMPLE | TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+            || !depth.contains(TextureFormatFeatureFlags::MULTISAMPLE) {
+            log::warn!("{:?} can't be multisampled on this adapter, MSAA is disabled", format);
+            return 1;
+        }
+
+        // only 4 samples are guaranteed, and wgpu 0.14 can't tell which other counts an adapter supports
+        let count = if self.msaa_samples >= 4 { 4 } else { 1 };
+        if count != self.msaa_samples {
+            log::warn!("{}x MSAA isn't supported, using {}x", self.msaa_samples, count);
+        }
+        count
+    }
+}
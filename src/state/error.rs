@@ -0,0 +1,50 @@
+use std::fmt;
+use wgpu::{Backends, RequestDeviceError};
+
+/// Why the renderer couldn't start.
+#[derive(Debug)]
+pub enum InitError {
+    /// No adapter matched, not even a software fallback.
+    NoAdapter {
+        /// The backends that were searched.
+        backends: Backends,
+    },
+    /// The adapter refused every device request, including the one with downlevel limits.
+    NoDevice {
+        /// Name of the adapter.
+        adapter: String,
+        /// Why the last request failed.
+        source: RequestDeviceError,
+    },
+    /// The starting shader didn't compile.
+    Shader(anyhow::Error),
+    /// The adapter another window renders with can't present to this one.
+    UnsupportedSurface {
+        /// Name of the adapter.
+        adapter: String,
+    },
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAdapter { backends } => write!(f, "No graphics adapter found for backends {:?}. \
+                Your GPU or browser may not support WebGPU or WebGL2, or its drivers may need updating.", backends),
+            Self::NoDevice { adapter, source } => write!(f, "The graphics adapter `{}` couldn't create a device ({}). \
+                It may be missing features or limits this renderer needs.", adapter, source),
+            Self::Shader(error) => write!(f, "The starting shader couldn't be compiled: {:#}", error),
+            Self::UnsupportedSurface { adapter } => write!(f, "The graphics adapter `{}` can't present to this window. \
+                Windows sharing a device need to be on displays the same GPU drives.", adapter),
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoAdapter { .. } | Self::UnsupportedSurface { .. } => None,
+            Self::NoDevice { source, .. } => Some(source),
+            Self::Shader(error) => Some(error.as_ref()),
+        }
+    }
+}
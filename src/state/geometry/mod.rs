@@ -1,81 +1,98 @@
-pub(super) mod quad;
+pub(crate) mod quad;
 pub(super) mod model;
 
 use bytemuck::Pod;
 use wgpu::{Buffer, BufferUsages, Device, util::{DeviceExt, BufferInitDescriptor}};
 
+/// Vertex and `u16` index buffers of a mesh, see [`MeshBuilder`].
 #[derive(Debug)]
-pub(super) struct VertexBinding {
+pub struct VertexBinding {
     vertex_buffer: Buffer,
     index_buffer: Buffer,
     num_indices: u32,
 }
 
 impl VertexBinding {
-    pub(super) fn vertex_buffer(&self) -> &Buffer {
+    /// Buffer of the vertices, in the layout they were built with.
+    pub fn vertex_buffer(&self) -> &Buffer {
         &self.vertex_buffer
     }
 
-    pub(super) fn index_buffer(&self) -> &Buffer {
+    /// Buffer of the `u16` indices.
+    pub fn index_buffer(&self) -> &Buffer {
         &self.index_buffer
     }
 
-    pub(super) fn num_indices(&self) -> u32 {
+    /// How many indices to draw.
+    pub fn num_indices(&self) -> u32 {
         self.num_indices
     }
 }
 
-pub(super) trait Vertex {
-    fn get_vertices(&self) -> &'static [u8];
+/// Vertices and indices to upload as a [`VertexBinding`].
+#[derive(Debug, Clone, Copy)]
+pub struct MeshBuilder<'a> {
+    label: &'a str,
+    vertices: &'a [u8],
+    indices: &'a [u16],
+}
 
-    fn get_indices(&self) -> &'static [u8];
+impl<'a> MeshBuilder<'a> {
+    /// `vertices` must match the vertex buffer layout of the pipeline drawing the mesh.
+    pub fn new<V: Pod>(vertices: &'a [V], indices: &'a [u16]) -> Self {
+        Self {
+            label: "Mesh",
+            vertices: bytemuck::cast_slice(vertices),
+            indices,
+        }
+    }
 
-    fn get_indices_number(&self) -> u32;
+    /// Prefix of the buffer labels, `Mesh` by default.
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = label;
+        self
+    }
 
-    fn make_binding(self, device: &Device) -> VertexBinding where Self: Sized + Pod {
+    /// Uploads the vertices and indices to `device`.
+    pub fn build(self, device: &Device) -> VertexBinding {
         let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: self.get_vertices(),
+            label: Some(&format!("{} Vertex Buffer", self.label)),
+            contents: self.vertices,
             usage: BufferUsages::VERTEX,
         });
         let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: self.get_indices(),
+            label: Some(&format!("{} Index Buffer", self.label)),
+            contents: bytemuck::cast_slice(self.indices),
             usage: BufferUsages::INDEX,
         });
-        let num_indices = self.get_indices_number();
         VertexBinding {
             vertex_buffer,
             index_buffer,
-            num_indices
+            num_indices: self.indices.len() as u32,
         }
     }
 }
 
-impl Vertex for quad::QuadVertex {
-    fn get_vertices(&self) -> &'static [u8] {
-        bytemuck::cast_slice(quad::VERTICES)
-    }
+pub(super) trait Vertex {
+    fn get_vertices(&self) -> &'static [u8];
 
-    fn get_indices(&self) -> &'static [u8] {
-        bytemuck::cast_slice(quad::INDICES)
-    }
+    fn get_indices(&self) -> &'static [u8];
 
-    fn get_indices_number(&self) -> u32 {
-        quad::INDICES.len() as u32
+    fn make_binding(self, device: &Device) -> VertexBinding where Self: Sized + Pod {
+        MeshBuilder {
+            label: "Mesh",
+            vertices: self.get_vertices(),
+            indices: bytemuck::cast_slice(self.get_indices()),
+        }.build(device)
     }
 }
 
-impl Vertex for model::ModelVertex {
+impl Vertex for quad::QuadVertex {
     fn get_vertices(&self) -> &'static [u8] {
-        todo!()
+        bytemuck::cast_slice(quad::VERTICES)
     }
 
     fn get_indices(&self) -> &'static [u8] {
-        todo!()
-    }
-
-    fn get_indices_number(&self) -> u32 {
-        todo!()
+        bytemuck::cast_slice(quad::INDICES)
     }
 }
@@ -1,10 +1,22 @@
-use std::ops::Range;
+use std::{borrow::Cow, cell::RefCell, io::BufReader, ops::Range, rc::Rc, sync::Arc};
+use anyhow::{anyhow, bail, Result};
 use bytemuck::{Pod, Zeroable};
+use cgmath::{Deg, InnerSpace, Matrix4, Vector3};
+use image::{DynamicImage, Rgba, RgbaImage};
 use wgpu::{
-    BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, BufferAddress,
-    Device, RenderPass, VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode
+    BindGroup, BindGroupLayout, BufferAddress, BufferUsages, Device, IndexFormat, PipelineLayoutDescriptor, Queue, RenderPass,
+    RenderPipeline, ShaderStages, VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode,
+    util::{BufferInitDescriptor, DeviceExt},
+    vertex_attr_array
 };
-use crate::state::texture::Texture;
+use crate::state::{
+    pipeline::{create_pipeline, PipelineDescription, RenderTarget, cache::{PipelineCache, RenderPipelineKey}},
+    shader::{compile_shader, ShaderCode, preprocessor::Preprocessor},
+    texture::Texture,
+    uniform::bundle::BindGroupBundle
+};
+
+const MODEL_SHADER: ShaderCode = ShaderCode::Wgsl(Cow::Borrowed(include_str!("../../model.wgsl")));
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -65,48 +77,22 @@ impl ModelVertex {
 }
 
 pub(in crate::state) struct Material {
-    pub(in crate::state) name: String,
-    pub(in crate::state) diffuse_texture: Texture,
-    pub(in crate::state) normal_texture: Texture,
-    pub(in crate::state) bind_group: BindGroup,
+    pub(in crate::state) bind_group: BindGroupBundle,
 }
 
 impl Material {
-    pub(in crate::state) fn new(device: &Device, name: &str, diffuse_texture: Texture, normal_texture: Texture, layout: &wgpu::BindGroupLayout) -> Self {
-        let bind_group = device.create_bind_group(&BindGroupDescriptor {
-            layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&diffuse_texture.view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: BindingResource::TextureView(&normal_texture.view),
-                },
-                BindGroupEntry {
-                    binding: 3,
-                    resource: BindingResource::Sampler(&normal_texture.sampler),
-                },
-            ],
-            label: Some(name),
-        });
+    /// Binds the diffuse texture and sampler at 0 and 1, the normal map and sampler at 2 and 3.
+    pub(in crate::state) fn new(device: &Device, cache: &mut PipelineCache, name: &str, diffuse_texture: &Texture, normal_texture: &Texture) -> Self {
+        let bind_group = BindGroupBundle::builder(name)
+            .texture(diffuse_texture, ShaderStages::FRAGMENT)
+            .texture(normal_texture, ShaderStages::FRAGMENT)
+            .build(device, cache);
 
-        Self {
-            name: String::from(name),
-            diffuse_texture,
-            normal_texture,
-            bind_group,
-        }
+        Self { bind_group }
     }
 }
 
 pub(in crate::state) struct Mesh {
-    pub(in crate::state) name: String,
     pub(in crate::state) vertex_buffer: wgpu::Buffer,
     pub(in crate::state) index_buffer: wgpu::Buffer,
     pub(in crate::state) num_elements: u32,
@@ -119,26 +105,18 @@ pub(in crate::state) struct Model {
 }
 
 pub(in crate::state) trait DrawModel<'a> {
-    fn draw_mesh(&mut self, mesh: &'a Mesh, material: &'a Material, camera_bind_group: &'a BindGroup, light_bind_group: &'a BindGroup);
-
     fn draw_mesh_instanced(&mut self, mesh: &'a Mesh, material: &'a Material, instances: Range<u32>, camera_bind_group: &'a BindGroup, light_bind_group: &'a BindGroup);
 
     fn draw_model(&mut self, model: &'a Model, camera_bind_group: &'a BindGroup, light_bind_group: &'a BindGroup);
 
     fn draw_model_instanced(&mut self, model: &'a Model, instances: Range<u32>, camera_bind_group: &'a BindGroup, light_bind_group: &'a BindGroup);
-
-    fn draw_model_instanced_with_material(&mut self, model: &'a Model, material: &'a Material, instances: Range<u32>, camera_bind_group: &'a BindGroup, light_bind_group: &'a BindGroup);
 }
 
 impl<'a, 'b> DrawModel<'b> for RenderPass<'a> where 'b: 'a {
-    fn draw_mesh(&mut self, mesh: &'b Mesh, material: &'b Material, camera_bind_group: &'b BindGroup, light_bind_group: &'b BindGroup) {
-        self.draw_mesh_instanced(mesh, material, 0..1, camera_bind_group, light_bind_group);
-    }
-
     fn draw_mesh_instanced(&mut self, mesh: &'b Mesh, material: &'b Material, instances: Range<u32>, camera_bind_group: &'b BindGroup, light_bind_group: &'b BindGroup) {
         self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        self.set_bind_group(0, &material.bind_group, &[]);
+        self.set_index_buffer(mesh.index_buffer.slice(..), IndexFormat::Uint32);
+        self.set_bind_group(0, material.bind_group.bind_group(), &[]);
         self.set_bind_group(1, camera_bind_group, &[]);
         self.set_bind_group(2, light_bind_group, &[]);
         self.draw_indexed(0..mesh.num_elements, 0, instances);
@@ -154,10 +132,228 @@ impl<'a, 'b> DrawModel<'b> for RenderPass<'a> where 'b: 'a {
             self.draw_mesh_instanced(mesh, material, instances.clone(), camera_bind_group, light_bind_group);
         }
     }
+}
 
-    fn draw_model_instanced_with_material(&mut self, model: &'b Model, material: &'b Material, instances: Range<u32>, camera_bind_group: &'b BindGroup, light_bind_group: &'b BindGroup) {
-        for mesh in &model.meshes {
-            self.draw_mesh_instanced(mesh, material, instances.clone(), camera_bind_group, light_bind_group);
+/// A mesh decoded on the CPU, indexing the materials of its [`ModelData`].
+#[derive(Debug, Clone)]
+pub(in crate::state) struct MeshData {
+    name: String,
+    vertices: Vec<ModelVertex>,
+    indices: Vec<u32>,
+    material: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(in crate::state) struct MaterialData {
+    name: String,
+    diffuse: Arc<RgbaImage>,
+}
+
+/// Meshes and materials of an OBJ file, kept to upload them again on a new device.
+#[derive(Debug, Clone)]
+pub(in crate::state) struct ModelData {
+    meshes: Vec<MeshData>,
+    materials: Vec<MaterialData>,
+}
+
+impl ModelData {
+    /// Parses an OBJ file, reading the material libraries and diffuse textures it names through `read`.
+    pub(in crate::state) fn from_obj(obj: &[u8], read: &dyn Fn(&str) -> Result<Vec<u8>>) -> Result<Self> {
+        // tobj only learns that a material library failed, not why
+        let failed = RefCell::new(None);
+        let (models, materials) = tobj::load_obj_buf(&mut BufReader::new(obj), &tobj::GPU_LOAD_OPTIONS, |path| {
+            let path = path.to_string_lossy();
+            match read(&path) {
+                Ok(bytes) => tobj::load_mtl_buf(&mut BufReader::new(bytes.as_slice())),
+                Err(error) => {
+                    failed.replace(Some(error.context(format!("material library `{}`", path))));
+                    Err(tobj::LoadError::OpenFileFailed)
+                }
+            }
+        })?;
+        if let Some(error) = failed.into_inner() {
+            return Err(error);
+        }
+        if models.is_empty() {
+            bail!("the file has no meshes");
+        }
+
+        let mut materials = materials?.into_iter().map(|material| {
+            let diffuse = match &material.diffuse_texture {
+                Some(path) => image::load_from_memory(&read(path)?)
+                    .map_err(|error| anyhow!("diffuse texture `{}`: {}", path, error))?
+                    .to_rgba8(),
+                None => solid(material.diffuse.unwrap_or([0.8; 3])),
+            };
+            Ok(MaterialData { name: material.name, diffuse: Arc::new(diffuse) })
+        }).collect::<Result<Vec<_>>>()?;
+        // meshes without a material use a plain one at the end
+        let fallback = materials.len();
+        materials.push(MaterialData { name: "Default Material".to_string(), diffuse: Arc::new(solid([0.8; 3])) });
+
+        let meshes = models.into_iter().map(|model| {
+            let mesh = model.mesh;
+            let mut vertices: Vec<ModelVertex> = (0..mesh.positions.len() / 3).map(|i| ModelVertex {
+                position: [mesh.positions[i * 3], mesh.positions[i * 3 + 1], mesh.positions[i * 3 + 2]],
+                // OBJ puts the texture origin in the bottom left corner
+                uv: mesh.texcoords.get(i * 2..i * 2 + 2).map_or([0.0; 2], |uv| [uv[0], 1.0 - uv[1]]),
+                normal: mesh.normals.get(i * 3..i * 3 + 3).map_or([0.0; 3], |n| [n[0], n[1], n[2]]),
+                ..ModelVertex::new()
+            }).collect();
+            if mesh.normals.is_empty() {
+                smooth_normals(&mut vertices, &mesh.indices);
+            }
+            MeshData {
+                name: model.name,
+                vertices,
+                indices: mesh.indices,
+                material: mesh.material_id.filter(|id| *id < fallback).unwrap_or(fallback),
+            }
+        }).collect();
+
+        Ok(Self { meshes, materials })
+    }
+}
+
+fn solid([r, g, b]: [f32; 3]) -> RgbaImage {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    RgbaImage::from_pixel(1, 1, Rgba([channel(r), channel(g), channel(b), 255]))
+}
+
+// averages the normals of the faces around every vertex
+fn smooth_normals(vertices: &mut [ModelVertex], indices: &[u32]) {
+    let mut normals = vec![Vector3::new(0.0f32, 0.0, 0.0); vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] as usize);
+        let position = |index: usize| Vector3::from(vertices[index].position);
+        let face = (position(b) - position(a)).cross(position(c) - position(a));
+        for index in [a, b, c] {
+            normals[index] += face;
+        }
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        if normal.magnitude2() > 0.0 {
+            vertex.normal = normal.normalize().into();
+        }
+    }
+}
+
+/// Where a model is placed, turned by `rotation` degrees around x, then y, then z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(in crate::state) struct Placement {
+    pub(in crate::state) position: [f32; 3],
+    pub(in crate::state) rotation: [f32; 3],
+    pub(in crate::state) scale: f32,
+}
+
+impl Placement {
+    fn matrix(&self) -> Matrix4<f32> {
+        let [x, y, z] = self.rotation;
+        Matrix4::from_translation(self.position.into())
+            * Matrix4::from_angle_z(Deg(z)) * Matrix4::from_angle_y(Deg(y)) * Matrix4::from_angle_x(Deg(x))
+            * Matrix4::from_scale(self.scale)
+    }
+}
+
+/// A model of a scene, where it's placed and how its triangles are drawn.
+#[derive(Debug, Clone)]
+pub(in crate::state) struct ModelDescriptor {
+    pub(in crate::state) data: Arc<ModelData>,
+    pub(in crate::state) placement: Placement,
+    pub(in crate::state) description: PipelineDescription,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+}
+
+impl InstanceRaw {
+    const ATTRIBS: [VertexAttribute; 4] = vertex_attr_array![5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4];
+
+    fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
         }
     }
+}
+
+/// A model uploaded to the GPU with the instance buffer placing it.
+pub(in crate::state) struct PlacedModel {
+    model: Model,
+    instance_buffer: wgpu::Buffer,
+    description: PipelineDescription,
+}
+
+impl PlacedModel {
+    pub(in crate::state) fn new(device: &Device, queue: &Queue, cache: &mut PipelineCache, desc: &ModelDescriptor) -> Self {
+        // untextured materials sample a flat normal map
+        let flat = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([128, 128, 255, 255])));
+        let materials = desc.data.materials.iter().map(|material| {
+            let diffuse = Texture::builder(&material.name)
+                .from_image(device, queue, &DynamicImage::ImageRgba8(material.diffuse.as_ref().clone()));
+            let normal = Texture::builder(&material.name).normal_map(true).from_image(device, queue, &flat);
+            Material::new(device, cache, &material.name, &diffuse, &normal)
+        }).collect();
+        let meshes = desc.data.meshes.iter().map(|mesh| Mesh {
+            vertex_buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(&format!("{} Vertex Buffer", mesh.name)),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: BufferUsages::VERTEX,
+            }),
+            index_buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(&format!("{} Index Buffer", mesh.name)),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: BufferUsages::INDEX,
+            }),
+            num_elements: mesh.indices.len() as u32,
+            material: mesh.material,
+        }).collect();
+        let instance_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Model Instance Buffer"),
+            contents: bytemuck::bytes_of(&InstanceRaw { model: desc.placement.matrix().into() }),
+            usage: BufferUsages::VERTEX,
+        });
+
+        Self { model: Model { meshes, materials }, instance_buffer, description: desc.description }
+    }
+
+    /// Layout of the material group, shared by every material.
+    pub(in crate::state) fn material_layout(&self) -> &BindGroupLayout {
+        self.model.materials[0].bind_group.layout()
+    }
+
+    /// Culling, topology and wireframe the model is drawn with.
+    pub(in crate::state) fn description(&self) -> PipelineDescription {
+        self.description
+    }
+
+    /// Draws with a pipeline from [`model_pipeline`] bound.
+    pub(in crate::state) fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, camera_bind_group: &'a BindGroup, light_bind_group: &'a BindGroup) {
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.draw_model(&self.model, camera_bind_group, light_bind_group);
+    }
+}
+
+/// Lit, textured pipeline drawing [`PlacedModel`]s, with the material, camera and light layouts at groups 0 to 2.
+pub(in crate::state) fn model_pipeline(device: &Device, cache: &mut PipelineCache, preprocessor: &Preprocessor, bind_group_layouts: [&BindGroupLayout; 3],
+                                       target: RenderTarget, description: PipelineDescription) -> Result<Rc<RenderPipeline>> {
+    let vertex_layouts = [ModelVertex::desc(), InstanceRaw::desc()];
+    let description = PipelineDescription { index_format: IndexFormat::Uint32, ..description };
+    let key = RenderPipelineKey::new(MODEL_SHADER.fingerprint(), &vertex_layouts, &bind_group_layouts, target, description);
+    let layout_entries: Vec<_> = bind_group_layouts.iter().map(|layout| cache.layout_entries(layout).to_vec()).collect();
+    cache.render_pipeline(key, || {
+        let shader = compile_shader(device, preprocessor, &MODEL_SHADER)?;
+        let groups: Vec<_> = layout_entries.iter().map(Vec::as_slice).collect();
+        shader.reflection().validate(&groups, &vertex_layouts)?;
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Model Pipeline Layout"),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        Ok(create_pipeline(device, &layout, target, &description, &vertex_layouts, &shader))
+    })
 }
\ No newline at end of file
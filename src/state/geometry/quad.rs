@@ -6,10 +6,13 @@ use wgpu::{
 };
 use super::VertexBinding;
 
+/// Vertex of the full-screen quad every fragment shader is drawn on.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
-pub(in crate::state) struct QuadVertex {
+pub struct QuadVertex {
+    /// Position in clip space.
     pub position:       [f32; 3],
+    /// Texture coordinates, `(0, 0)` at the top left.
     pub uv:             [f32; 2],
 }
 
@@ -23,7 +26,8 @@ impl QuadVertex {
         }
     }
 
-    pub(in crate::state) fn desc<'pipeline>() -> VertexBufferLayout<'pipeline> {
+    /// Layout of the vertex buffer, at locations 0 and 1.
+    pub fn desc<'pipeline>() -> VertexBufferLayout<'pipeline> {
         use std::mem;
         VertexBufferLayout {
             array_stride: mem::size_of::<QuadVertex>() as BufferAddress,
@@ -44,9 +48,12 @@ pub(in crate::state) const VERTICES: &[QuadVertex] = &[
 // a simple quad shape
 pub(in crate::state) const INDICES: &[u16] = &[2, 3, 0, 1, 2, 0];
 
-pub(in crate::state) trait DrawQuad<'a> {
+/// Draws a [`VertexBinding`] with a bound pipeline.
+pub trait DrawQuad<'a> {
+    /// Draws the mesh once.
     fn draw_mesh(&mut self, vertex_binding: &'a VertexBinding);
 
+    /// Draws the mesh once for each of `instances`.
     fn draw_mesh_instanced(&mut self, instances: Range<u32>, vertex_binding: &'a VertexBinding);
 }
 
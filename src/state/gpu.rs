@@ -0,0 +1,165 @@
+use std::{cell::RefCell, rc::Rc, sync::{Arc, atomic::{AtomicBool, Ordering}}};
+use wgpu::{
+    Adapter, Backend, Device, DeviceDescriptor, DownlevelFlags, Features, Instance, Limits, Queue, RequestAdapterOptions, Surface
+};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use super::{config::RendererConfig, error::InitError, pipeline::cache::PipelineCache};
+
+// asks for an adapter matching `config`, then for any software adapter
+async fn request_adapter(instance: &Instance, surface: Option<&Surface>, config: &RendererConfig) -> Result<Adapter, InitError> {
+    let attempts: &[bool] = if config.force_fallback_adapter { &[true] } else { &[false, true] };
+    for &force_fallback_adapter in attempts {
+        let adapter = instance.request_adapter(&RequestAdapterOptions {
+            power_preference: config.power_preference,
+            compatible_surface: surface,
+            force_fallback_adapter,
+        }).await;
+        match adapter {
+            Some(adapter) => return Ok(adapter),
+            None if !force_fallback_adapter => log::warn!("No hardware adapter found, trying a fallback adapter"),
+            None => {}
+        }
+    }
+    Err(InitError::NoAdapter { backends: config.backends })
+}
+
+// asks for the default limits first and settles for the downlevel ones
+async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), InitError> {
+    let candidates = if cfg!(target_arch = "wasm32") {
+        vec![Limits::downlevel_webgl2_defaults()]
+    } else {
+        vec![Limits::default(), Limits::downlevel_defaults(), Limits::downlevel_webgl2_defaults()]
+    };
+
+    let mut last_error = None;
+    for limits in candidates {
+        let descriptor = DeviceDescriptor {
+            label: None,
+            // sample counts other than 4 for multisampling, and wireframes, where the adapter allows them
+            features: adapter.features() & (Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES | Features::POLYGON_MODE_LINE),
+            // never ask for more than the adapter offers, e.g. its maximum texture size
+            limits: limits.using_resolution(adapter.limits()),
+        };
+        match adapter.request_device(&descriptor, None).await {
+            Ok(device) => return Ok(device),
+            Err(error) => {
+                log::warn!("Couldn't create a device with {:?}: {}", descriptor.limits, error);
+                last_error = Some(error);
+            }
+        }
+    }
+    Err(InitError::NoDevice {
+        adapter: adapter.get_info().name,
+        source: last_error.expect("at least one set of limits is tried"),
+    })
+}
+
+// wgpu reports a lost device as a validation error caused somewhere down the chain by wgpu-core's `DeviceError::Lost`
+fn is_device_lost(error: &wgpu::Error) -> bool {
+    let wgpu::Error::Validation { source, .. } = error else {
+        return false;
+    };
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(source.as_ref());
+    while let Some(error) = source {
+        if let Some(wgpu_core::device::DeviceError::Lost) = error.downcast_ref() {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// What the adapter a device came from can run, beyond what the device's limits tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Capabilities {
+    pub(super) compute_shaders: bool,
+    // storage buffers read in vertex shaders, as particles are
+    pub(super) vertex_storage: bool,
+}
+
+impl Capabilities {
+    pub(crate) fn new(adapter: &Adapter) -> Self {
+        let flags = adapter.get_downlevel_capabilities().flags;
+        // wgpu-hal's GL backend panics on the storage textures compute shaders write ("Unsupported uniform datatype!")
+        let gl = adapter.get_info().backend == Backend::Gl;
+        Self {
+            compute_shaders: flags.contains(DownlevelFlags::COMPUTE_SHADERS) && !gl,
+            vertex_storage: flags.contains(DownlevelFlags::VERTEX_STORAGE),
+        }
+    }
+}
+
+fn log_adapter(adapter: &Adapter) {
+    let info = adapter.get_info();
+    log::info!("Using {} ({:?}, {:?})", info.name, info.backend, info.device_type);
+}
+
+/// A device for rendering offscreen, on any adapter matching `config`, and the adapter it came from.
+pub(super) async fn request_headless(config: &RendererConfig) -> Result<(Adapter, Device, Queue), InitError> {
+    let instance = Instance::new(config.backends);
+    let adapter = request_adapter(&instance, None, config).await?;
+    let (device, queue) = request_device(&adapter).await?;
+    log_adapter(&adapter);
+    Ok((adapter, device, queue))
+}
+
+/// Connection to a GPU, shared by the windows rendering with it along with the pipelines built on it.
+#[derive(Debug)]
+pub(crate) struct GpuContext {
+    // surfaces of further windows are created from the instance the adapter came from
+    pub(super) instance: Instance,
+    pub(super) adapter: Adapter,
+    pub(super) device: Device,
+    pub(super) queue: Queue,
+    /// Set from the uncaptured error handler once the device is gone.
+    pub(super) lost: Arc<AtomicBool>,
+    pub(super) pipelines: Rc<RefCell<PipelineCache>>,
+}
+
+/// A window surface and the GPU connection it presents with, created again when the device is lost.
+#[derive(Debug)]
+pub(crate) struct Gpu {
+    pub(super) surface: Surface,
+    pub(super) context: Rc<GpuContext>,
+}
+
+impl Gpu {
+    /// # Safety
+    /// `window` must outlive the returned surface.
+    pub(crate) async unsafe fn new<W: HasRawWindowHandle + HasRawDisplayHandle>(config: &RendererConfig, window: &W) -> Result<Self, InitError> {
+        // The instance is a handle to our GPU
+        // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
+        let instance = Instance::new(config.backends);
+        let surface = instance.create_surface(window);
+        let adapter = request_adapter(&instance, Some(&surface), config).await?;
+        let (device, queue) = request_device(&adapter).await?;
+        log_adapter(&adapter);
+
+        let lost = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&lost);
+        device.on_uncaptured_error(move |error| {
+            if is_device_lost(&error) {
+                log::error!("Device lost: {}", error);
+                flag.store(true, Ordering::Relaxed);
+            } else {
+                // the device stays usable after validation errors and running out of memory, only the frame suffers
+                log::error!("Unhandled wgpu error: {}", error);
+            }
+        });
+
+        let pipelines = Rc::default();
+        Ok(Self { surface, context: Rc::new(GpuContext { instance, adapter, device, queue, lost, pipelines }) })
+    }
+
+    /// A surface for `window` on the connection of another one.
+    ///
+    /// # Safety
+    /// `window` must outlive the returned surface.
+    pub(crate) unsafe fn share<W: HasRawWindowHandle + HasRawDisplayHandle>(context: &Rc<GpuContext>, window: &W) -> Result<Self, InitError> {
+        let surface = context.instance.create_surface(window);
+        if !context.adapter.is_surface_supported(&surface) {
+            return Err(InitError::UnsupportedSurface { adapter: context.adapter.get_info().name });
+        }
+        Ok(Self { surface, context: Rc::clone(context) })
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use image::RgbaImage;
+use wgpu::{
+    CommandEncoderDescriptor, Device, Extent3d, Maintain, Queue, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor
+};
+use super::{scene::Scene, texture::readback::Readback, time::Duration};
+
+/// Renders a [`Scene`] into a texture without a window and reads the frames back,
+/// e.g. to export images or compare them in tests.
+///
+/// Created with [`RendererBuilder::build_headless`](crate::RendererBuilder::build_headless).
+pub struct HeadlessRenderer {
+    device: Device,
+    queue: Queue,
+    scene: Scene,
+    texture: wgpu::Texture,
+    view: TextureView,
+}
+
+impl HeadlessRenderer {
+    /// Format of the frames, in sRGB like most window surfaces.
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+    pub(super) fn new(device: Device, queue: Queue, scene: Scene) -> Self {
+        let (width, height) = scene.size();
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Headless Target"),
+            size: Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        Self { device, queue, scene, texture, view }
+    }
+
+    /// The device the scene and its target live on.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// The queue frames and uploads are submitted to.
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// The shaders, particles and camera drawn into the texture.
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// The scene, to select shaders, move the camera or set the time between frames.
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    /// Advances the scene by `dt`, see [`Scene::update`].
+    pub fn update(&mut self, dt: Duration) {
+        self.scene.update(&self.queue, dt);
+    }
+
+    /// Draws a frame into the texture.
+    pub fn render(&mut self) {
+        let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor { label: Some("Headless Encoder") });
+        self.scene.record(&mut encoder, &self.view);
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Reads the last rendered frame back, waiting for the GPU to finish it.
+    pub async fn read_pixels(&self) -> Result<RgbaImage> {
+        let readback = Readback::new(&self.device, &self.queue, &self.texture, Self::FORMAT, self.scene.size())?;
+        // browsers map buffers on their own, polling a native device blocks until the mapping is done
+        self.device.poll(Maintain::Wait);
+        readback.await
+    }
+}
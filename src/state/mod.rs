@@ -1,279 +1,355 @@
-mod geometry;
-mod uniform;
-mod time;
-mod camera;
-mod texture;
+pub(super) mod config;
+pub(super) mod error;
+pub(super) mod gpu;
+pub(super) mod builder;
+pub(super) mod scene;
+pub(super) mod scene_file;
+pub(super) mod headless;
+pub(super) mod geometry;
+pub(super) mod uniform;
+pub(super) mod time;
+pub(super) mod camera;
+pub(super) mod texture;
+pub(super) mod shader;
+pub(super) mod compute;
+pub(super) mod compare;
+pub(super) mod window;
+pub(super) mod screenshot;
+mod particle;
+mod pipeline;
 
-use std::{borrow::Cow, iter};
+pub use self::pipeline::cache::CacheStats;
+
+use std::{iter, rc::Rc, sync::atomic::Ordering};
+use anyhow::Result;
+use bytemuck::Pod;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 use wgpu::*;
-use winit::{dpi::PhysicalSize, event::*, window::Window};
-use time::Instant;
+use winit::{dpi::PhysicalSize, event::*};
+use time::Duration;
 use self::{
-    camera::{Camera, Projection, controller::CameraController},
-    geometry::{Vertex, VertexBinding, quad::{QuadVertex, DrawQuad}},
-    uniform::{Uniform, UniformBinding, system::SystemUniform, camera::CameraUniform}
+    builder::RendererBuilder,
+    config::RendererConfig,
+    error::InitError,
+    gpu::{Capabilities, Gpu, GpuContext},
+    pipeline::RenderTarget,
+    scene::{Scene, SceneContent},
+    screenshot::Screenshot,
+    texture::Texture,
+    shader::library::{ShaderLibrary, ShaderProgram},
+    uniform::{Flush, Uniform, UniformBinding, bundle::{BindGroupBundle, BundleBuilder}, dynamic::DynamicUniformBuffer}
 };
-#[cfg(target_arch="wasm32")]
-use wasm_bindgen::prelude::*;
-
-fn compile_shader(device: &Device, source: &str) -> ShaderModule {
-    device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("Shader"),
-        source: ShaderSource::Wgsl(Cow::Borrowed(source))
-    })
-}
 
-fn create_pipeline(device: &Device, layout: &PipelineLayout, color_format: TextureFormat, depth_format: Option<TextureFormat>, vertex_layouts: &[VertexBufferLayout], shader: ShaderModule) -> RenderPipeline {
-    device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: Some("Render Pipeline"),
-        layout: Some(layout),
-        // vertex shader and buffers
-        vertex: VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: vertex_layouts,
-        },
-        // fragment shader and buffers and blending modes
-        fragment: Some(FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(ColorTargetState {
-                format: color_format,
-                blend: Some(BlendState {
-                    alpha: BlendComponent::REPLACE,
-                    color: BlendComponent::REPLACE,
-                }),
-                write_mask: ColorWrites::ALL,
-            })],
-        }),
-        // how to interpret vertices as triangles
-        primitive: PrimitiveState {
-            topology: PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: FrontFace::Ccw,
-            cull_mode: Some(Face::Back),
-            // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE or Features::POLYGON_MODE_POINT
-            polygon_mode: PolygonMode::Fill,
-            // Requires Features::DEPTH_CLIP_CONTROL
-            unclipped_depth: false,
-            // Requires Features::CONSERVATIVE_RASTERIZATION
-            conservative: false,
-        },
-        depth_stencil: depth_format.map(|format| DepthStencilState {
-            format,
-            depth_write_enabled: true,
-            depth_compare: CompareFunction::Less,
-            stencil: StencilState::default(),
-            bias: DepthBiasState::default(),
-        }),
-        multisample: MultisampleState {
-            count: 1,
-            mask: !0,
-            // No antialiasing
-            alpha_to_coverage_enabled: false,
-        },
-        // If the pipeline will be used with a multiview render pass, this indicates how many array layers the attachments will have.
-        multiview: None,
-    })
-}
-
-pub(super) struct State {
-    surface: Surface,
+/// Draws a fragment shader over a full-screen quad into a window surface, optionally fed by a
+/// compute pass and overlaid with particles.
+///
+/// Created with [`Renderer::builder`]. Every frame, call [`update`](Self::update) with the time
+/// since the previous frame, then [`render`](Self::render). Renderers of further windows can share
+/// the device, queue and pipelines of the first with [`RendererBuilder::build_sharing`].
+pub struct Renderer {
+    config: RendererConfig,
+    // released while a new device is requested, Vulkan won't create a second surface for the window
+    surface: Option<Surface>,
     surface_config: SurfaceConfiguration,
-    device: Device,
-    queue: Queue,
-    render_pipeline: RenderPipeline,
-    render_pipeline_layout: PipelineLayout,
-    mesh: VertexBinding,
-    /* binding */
-    size: PhysicalSize<u32>,
-    start_render_time: Instant,
-    last_render_time: Instant,
-    mouse_lock: bool,
-    camera: Camera,
-    projection: Projection,
-    camera_controller: CameraController,
-    system_uniform: UniformBinding<SystemUniform>,
-    camera_uniform: UniformBinding<CameraUniform>,
+    gpu: Rc<GpuContext>,
+    scene: Scene,
 }
 
-#[cfg_attr(target_arch="wasm32", wasm_bindgen)]
-impl State {
-    pub(super) async fn new(source: &str, window: &Window) -> Self {
-        let size = window.inner_size();
-        let start_render_time = Instant::now();
-        let last_render_time = Instant::now();
-        // The instance is a handle to our GPU
-        // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
-        let instance = Instance::new(Backends::all());
-        let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance.request_adapter(&RequestAdapterOptions {
-            power_preference: PowerPreference::default(),
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        }).await.unwrap();
-
-        let (device, queue) = adapter.request_device(
-            &DeviceDescriptor {
-                label: None,
-                features: Features::empty(),
-                limits: if cfg!(target_arch = "wasm32") { Limits::downlevel_webgl2_defaults() } else { Limits::default() },
-            },
-            None, // Trace path
-        ).await.unwrap();
+impl Renderer {
+    /// Settings to create a renderer with, starting from the defaults.
+    pub fn builder() -> RendererBuilder {
+        RendererBuilder::default()
+    }
+
+    // picks the shader to start with, loading `shader` from disk when it names a file
+    fn initial_shader(library: &mut ShaderLibrary, shader: &str) -> String {
+        #[cfg(not(target_arch = "wasm32"))]
+        if library.get(shader).is_none() && std::path::Path::new(shader).is_file() {
+            match library.load(std::path::Path::new(shader)) {
+                Ok(name) => return name,
+                Err(error) => log::error!("Couldn't load shader `{}`: {:#}", shader, error),
+            }
+        }
+
+        if library.get(shader).is_none() {
+            log::warn!("Unknown shader `{}`, available: {}", shader, library.names().collect::<Vec<_>>().join(", "));
+            return library.current().to_string();
+        }
+        shader.to_string()
+    }
 
+    // configures the surface of `gpu` for `config`, returning the target pipelines are built for
+    fn configure_surface(gpu: &Gpu, config: &RendererConfig, size: PhysicalSize<u32>) -> (SurfaceConfiguration, RenderTarget) {
+        let choice = config.choose(&gpu.surface, &gpu.context.adapter);
+        log::info!("Surface uses {:?}, {:?} presentation, {:?} alpha and {}x MSAA", choice.format, choice.present_mode, choice.alpha_mode, choice.sample_count);
         let surface_config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
+            format: choice.format,
             width: size.width,
             height: size.height,
-            present_mode: PresentMode::Fifo,
-            alpha_mode: CompositeAlphaMode::Auto,
+            present_mode: choice.present_mode,
+            alpha_mode: choice.alpha_mode,
         };
-        surface.configure(&device, &surface_config);
-
-        // SYSTEM BINDING
-        let system_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("Time Buffer Bind Group Layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::VERTEX_FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-        let system_uniform = SystemUniform::new(size, start_render_time).make_binding(&device, &system_bind_group_layout);
-
-        // CAMERA BINDING
-        let camera_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("Camera Buffer Bind Group Layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::VERTEX_FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-        let camera_uniform = CameraUniform::new().make_binding(&device, &camera_bind_group_layout);
+        gpu.surface.configure(&gpu.context.device, &surface_config);
+        let target = RenderTarget {
+            color_format: choice.format,
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count: choice.sample_count,
+        };
+        (surface_config, target)
+    }
 
-        let shader = compile_shader(&device, source);
-        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&system_bind_group_layout, &camera_bind_group_layout],
-            push_constant_ranges: &[],
-        });
-        let render_pipeline = create_pipeline(&device, &render_pipeline_layout, surface_config.format, None, &[QuadVertex::desc()], shader);
+    // creates every GPU resource on `gpu` from the CPU-side descriptions
+    fn with_gpu(gpu: Gpu, config: RendererConfig, library: ShaderLibrary, shader: &str, particles: Option<&str>,
+                content: SceneContent, size: PhysicalSize<u32>) -> Result<Self, InitError> {
+        let (surface_config, target) = Self::configure_surface(&gpu, &config, size);
+        let Gpu { surface, context } = gpu;
+        let scene = Scene::new(&context.device, &context.queue, Capabilities::new(&context.adapter), Rc::clone(&context.pipelines),
+                               library, shader, particles, content, target, size)?;
+        Ok(Self { config, surface: Some(surface), surface_config, gpu: context, scene })
+    }
+
+    pub(crate) fn gpu(&self) -> &Rc<GpuContext> {
+        &self.gpu
+    }
 
-        let mesh = QuadVertex::new().make_binding(&device);
+    /// The settings the surface is configured with.
+    pub fn config(&self) -> &RendererConfig {
+        &self.config
+    }
 
-        let camera = Camera::new((0.0, 5.0, 10.0), cgmath::Deg(-90.0), cgmath::Deg(-20.0));
-        let projection = Projection::new(size.width, size.height, cgmath::Deg(45.0), 0.1, 100.0);
-        let camera_controller = CameraController::new(4.0, 0.4);
+    /// The device every GPU resource of the renderer lives on, shared with windows built alongside it.
+    pub fn device(&self) -> &Device {
+        &self.gpu.device
+    }
 
-        Self {
-            surface, device, queue, surface_config, render_pipeline, render_pipeline_layout,
-            mesh, size, start_render_time, last_render_time, mouse_lock: false,
-            camera, projection, camera_controller, system_uniform, camera_uniform
-        }
+    /// The queue frames and uploads are submitted to.
+    pub fn queue(&self) -> &Queue {
+        &self.gpu.queue
+    }
+
+    /// The shaders, particles and camera drawn into the window.
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// The scene, to change it beyond what the renderer's own methods do.
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    /// Whether the device has been lost, after which nothing renders until [`recover`](Self::recover).
+    pub fn device_lost(&self) -> bool {
+        self.gpu.lost.load(Ordering::Relaxed)
     }
 
-    pub(super) fn recompile(&mut self, source: &str) {
-        self.render_pipeline = create_pipeline(&self.device, &self.render_pipeline_layout, self.surface_config.format, None,
-                                               &[QuadVertex::desc()], compile_shader(&self.device, source))
+    /// Connects to the GPU again after the device was lost and recreates every GPU resource.
+    ///
+    /// # Safety
+    /// `window` must be the window the renderer was built for and outlive the renderer.
+    pub async unsafe fn recover<W: HasRawWindowHandle + HasRawDisplayHandle>(&mut self, window: &W) -> Result<(), InitError> {
+        self.release_surface();
+        let gpu = Gpu::new(&self.config, window).await?;
+        self.rebuild(gpu)
     }
 
-    pub(super) fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.surface_config.width = new_size.width;
-            self.surface_config.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+    /// Moves the renderer onto the device `other` recovered with, after both lost theirs.
+    ///
+    /// # Safety
+    /// `window` must be the window the renderer was built for and outlive the renderer.
+    pub unsafe fn recover_sharing<W: HasRawWindowHandle + HasRawDisplayHandle>(&mut self, window: &W, other: &Renderer) -> Result<(), InitError> {
+        // the window can't have a second surface while the old one is configured
+        self.release_surface();
+        let gpu = Gpu::share(&other.gpu, window)?;
+        self.rebuild(gpu)
+    }
+
+    /// Drops the surface of the lost device so a new one can be created for the window.
+    /// Nothing is presented until [`rebuild`](Self::rebuild).
+    pub(crate) fn release_surface(&mut self) {
+        self.surface = None;
+    }
+
+    /// Recreates pipelines, uniforms, meshes and textures on a new device, keeping the
+    /// loaded shaders, particle definitions, camera and clock.
+    pub(crate) fn rebuild(&mut self, gpu: Gpu) -> Result<(), InitError> {
+        let size = PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+        let (surface_config, target) = Self::configure_surface(&gpu, &self.config, size);
+        let Gpu { surface, context } = gpu;
+        self.scene.rebuild(&context.device, &context.queue, Capabilities::new(&context.adapter), Rc::clone(&context.pipelines), target)?;
+        (self.surface, self.surface_config, self.gpu) = (Some(surface), surface_config, context);
+        log::info!("Rebuilt GPU resources after device loss");
+        Ok(())
+    }
+
+    /// Registers `program` under `name`, replacing any shader called the same, without selecting it.
+    pub fn add_shader(&mut self, name: &str, program: ShaderProgram) {
+        self.scene.add_shader(name, program);
+    }
+
+    /// Reads a `.wgsl`, `.glsl`/`.frag` or `.spv` file and registers it under its file stem, which is returned.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_shader(&mut self, path: &std::path::Path) -> Result<String> {
+        self.scene.load_shader(path)
+    }
+
+    /// Every registered shader name, in cycling order.
+    pub fn shader_names(&self) -> impl Iterator<Item = &str> {
+        self.scene.shader_names()
+    }
+
+    /// Name of the shader being drawn.
+    pub fn current_shader(&self) -> &str {
+        self.scene.current_shader()
+    }
+
+    /// How often pipelines were reused rather than compiled, on this renderer's device and every window sharing it.
+    pub fn pipeline_stats(&self) -> CacheStats {
+        self.scene.pipeline_stats()
+    }
+
+    /// Compiles the shader called `name`, or reuses its pipelines, and draws with it from the next frame.
+    pub fn select_shader(&mut self, name: &str) -> Result<()> {
+        self.scene.select_shader(&self.gpu.device, &self.gpu.queue, name)
+    }
+
+    /// Draws the shader called `name` next to the current one, see [`Scene::compare`].
+    pub fn compare(&mut self, name: &str) -> Result<()> {
+        self.scene.compare(&self.gpu.device, &self.gpu.queue, name)
+    }
+
+    /// Draws the current shader alone again.
+    pub fn stop_comparing(&mut self) {
+        self.scene.stop_comparing();
+    }
+
+    /// Removes the particles, or starts simulating them from the definitions the renderer was built with.
+    pub fn toggle_particles(&mut self) {
+        self.scene.toggle_particles(&self.gpu.device);
+    }
+
+    /// Applies the surface settings of `config` right away, rebuilding the pipelines when the format or sample count changes.
+    pub fn set_config(&mut self, config: RendererConfig) {
+        if config.power_preference != self.config.power_preference || config.backends != self.config.backends {
+            log::warn!("Power preference and backends only apply when the device is created");
         }
+        let Some(surface) = &self.surface else {
+            // applied once the surface is created again
+            self.config = config;
+            return;
+        };
+        let choice = config.choose(surface, &self.gpu.adapter);
+        log::info!("Surface uses {:?}, {:?} presentation, {:?} alpha and {}x MSAA", choice.format, choice.present_mode, choice.alpha_mode, choice.sample_count);
+        self.surface_config.format = choice.format;
+        self.surface_config.present_mode = choice.present_mode;
+        self.surface_config.alpha_mode = choice.alpha_mode;
+        surface.configure(&self.gpu.device, &self.surface_config);
+        self.config = config;
+        self.scene.set_target(&self.gpu.device, &self.gpu.queue, RenderTarget {
+            color_format: choice.format,
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count: choice.sample_count,
+        });
     }
 
-    pub(super) fn current_size(&self) -> PhysicalSize<u32> {
-        self.size
+    /// Steps through the present modes the surface supports.
+    fn cycle_present_mode(&mut self) {
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        let modes = surface.get_supported_present_modes(&self.gpu.adapter);
+        let next = match modes.iter().position(|mode| *mode == self.surface_config.present_mode) {
+            Some(index) => modes[(index + 1) % modes.len()],
+            None => modes[0],
+        };
+        self.set_config(RendererConfig { present_mode: next, ..self.config.clone() });
     }
 
-    pub(super) fn handle_mouse_input(&mut self, x: f64, y: f64) {
-        if self.mouse_lock {
-            let _ = &self.camera_controller.process_mouse(x, y);
+    /// Resizes the surface, ignoring zero sizes such as those of minimized windows.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.surface_config.width = width;
+            self.surface_config.height = height;
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.gpu.device, &self.surface_config);
+            }
+            self.scene.resize(&self.gpu.device, width, height);
         }
     }
 
-    pub(super) fn handle_input(&mut self, event: &WindowEvent) -> bool {
+    /// Size of the surface in physical pixels.
+    pub fn size(&self) -> (u32, u32) {
+        self.scene.size()
+    }
+
+    /// Turns the camera by raw mouse motion while the left button is held.
+    pub fn handle_mouse_input(&mut self, x: f64, y: f64) {
+        self.scene.handle_mouse_input(x, y);
+    }
+
+    /// Handles the shader, particle, present mode and camera controls, returning whether `event` was consumed.
+    pub fn handle_input(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::KeyboardInput {
                 input:
                 KeyboardInput {
-                    virtual_keycode: Some(key),
-                    state,
+                    virtual_keycode: Some(VirtualKeyCode::V),
+                    state: ElementState::Pressed,
                     ..
                 },
                 ..
-            } => self.camera_controller.process_keyboard(*key, *state),
-            WindowEvent::MouseWheel { delta, .. } => {
-                self.camera_controller.process_scroll(delta);
-                true
-            }
-            WindowEvent::MouseInput {
-                button: MouseButton::Left,
-                state,
-                ..
             } => {
-                self.mouse_lock = *state == ElementState::Pressed;
+                self.cycle_present_mode();
                 true
             }
-            _ => false,
+            _ => self.scene.handle_input(&self.gpu.device, &self.gpu.queue, event),
         }
     }
 
-    pub(super) fn update(&mut self) {
-        self.camera_controller.update_camera(&mut self.camera, Instant::now() - self.last_render_time);
-        self.camera_uniform.uniform_mut().update_view_proj(&self.camera, &self.projection);
-        self.system_uniform.uniform_mut().update_system(self.size, self.start_render_time);
-        self.queue.write_buffer(self.system_uniform.buffer(), 0, bytemuck::cast_slice(&[*self.system_uniform.uniform()]));
-        self.queue.write_buffer(self.camera_uniform.buffer(), 0, bytemuck::cast_slice(&[*self.camera_uniform.uniform()]));
+    /// Advances the camera and particles by `dt` and uploads the uniforms for the next frame.
+    pub fn update(&mut self, dt: Duration) {
+        self.scene.update(&self.gpu.queue, dt);
     }
 
-    pub(super) fn render(&mut self) -> Result<(), SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output.texture.create_view(&TextureViewDescriptor::default());
-        let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor { label: Some("Render Encoder") });
-
-        encoder.push_debug_group("rendering passes");
-        {
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, self.system_uniform.bind_group(), &[]);
-            render_pass.set_bind_group(1, self.camera_uniform.bind_group(), &[]);
-            render_pass.draw_mesh(&self.mesh);
-        }
-        encoder.pop_debug_group();
+    /// Uploads a new uniform with a bind group of its own, sharing the layout with every uniform of its type.
+    pub fn create_uniform<T: Uniform + Pod>(&mut self, uniform: T) -> UniformBinding<T> {
+        self.scene.create_uniform(&self.gpu.device, uniform)
+    }
 
-        self.queue.submit(iter::once(encoder.finish()));
+    /// Allocates room for `capacity` uniforms bound with dynamic offsets.
+    pub fn create_dynamic_uniforms<T: Uniform + Pod>(&mut self, capacity: usize) -> DynamicUniformBuffer<T> {
+        self.scene.create_dynamic_uniforms(&self.gpu.device, capacity)
+    }
+
+    /// Creates the bind group `builder` describes, sharing its layout with every group of the same entries.
+    pub fn create_bind_group(&mut self, builder: BundleBuilder) -> BindGroupBundle {
+        self.scene.create_bind_group(&self.gpu.device, builder)
+    }
+
+    /// Uploads every changed uniform in `uniforms`, returning how many were written.
+    pub fn flush(&self, uniforms: &mut [&mut dyn Flush]) -> usize {
+        self.scene.flush(&self.gpu.queue, uniforms)
+    }
+
+    /// Draws a frame and presents it. Lost or outdated surfaces are fixed by calling
+    /// [`resize`](Self::resize) with the current size.
+    pub fn render(&mut self) -> Result<(), SurfaceError> {
+        let output = self.surface.as_ref().ok_or(SurfaceError::Lost)?.get_current_texture()?;
+        let view = output.texture.create_view(&TextureViewDescriptor::default());
+        let mut encoder = self.gpu.device.create_command_encoder(&CommandEncoderDescriptor { label: Some("Render Encoder") });
+        self.scene.record(&mut encoder, &view);
+        self.gpu.queue.submit(iter::once(encoder.finish()));
         output.present();
 
         Ok(())
     }
+
+    /// Draws the last frame again at `scale` times the window size and starts reading it back.
+    ///
+    /// Surfaces can't always be copied from, WebGL ones never, so the frame is drawn into a texture of its own.
+    /// Natively the screenshot only resolves once the [device](Self::device) is polled.
+    pub fn screenshot(&mut self, scale: u32) -> Result<Screenshot> {
+        let readback = self.scene.capture(&self.gpu.device, &self.gpu.queue, scale)?;
+        Ok(Screenshot::new(readback))
+    }
 }
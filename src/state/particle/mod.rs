@@ -0,0 +1,277 @@
+use std::{borrow::Cow, rc::Rc};
+use anyhow::{bail, Result};
+use bytemuck::{Pod, Zeroable};
+use serde::Deserialize;
+use wgpu::{
+    BindGroupLayout, BufferAddress, BufferDescriptor, BufferUsages, CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    Device, PipelineLayoutDescriptor, Queue, RenderPass, RenderPipeline, ShaderStages,
+    util::{BufferInitDescriptor, DeviceExt}
+};
+use wgsl_layout::wgsl_layout;
+use crate::state::{
+    pipeline::{
+        create_pipeline, BlendPreset, PipelineDescription, RenderTarget,
+        cache::{ComputePipelineKey, PipelineCache, RenderPipelineKey}
+    },
+    geometry::{VertexBinding, quad::{DrawQuad, QuadVertex}},
+    shader::{compile_shader, ShaderCode, preprocessor::Preprocessor},
+    uniform::{Flush, Uniform, UniformBinding, bundle::BindGroupBundle},
+    time::Duration
+};
+
+pub(in crate::state) const WORKGROUP_SIZE: u32 = 64;
+const UPDATE_SHADER: ShaderCode = ShaderCode::Wgsl(Cow::Borrowed(include_str!("../../particle_update.wgsl")));
+const RENDER_SHADER: ShaderCode = ShaderCode::Wgsl(Cow::Borrowed(include_str!("../../particle_render.wgsl")));
+
+/// Emitter definitions bundled with the binary.
+pub(in crate::state) const FOUNTAIN: &str = include_str!("../../fountain.ron");
+
+/// Particle system definition, usually read from a RON file.
+#[derive(Debug, Clone, Deserialize)]
+pub(in crate::state) struct ParticleSystemDescriptor {
+    #[serde(default = "default_gravity")]
+    pub(in crate::state) gravity: [f32; 3],
+    #[serde(default)]
+    pub(in crate::state) drag: f32,
+    #[serde(default = "default_blend")]
+    pub(in crate::state) blend: BlendPreset,
+    pub(in crate::state) emitters: Vec<EmitterDescriptor>,
+}
+
+/// A point spawning `count` particles in a cone around `direction`.
+#[derive(Debug, Clone, Deserialize)]
+pub(in crate::state) struct EmitterDescriptor {
+    pub(in crate::state) position: [f32; 3],
+    pub(in crate::state) direction: [f32; 3],
+    /// Number of particles alive at once.
+    pub(in crate::state) count: u32,
+    /// Mean lifetime in seconds, each particle lives between half and all of it.
+    pub(in crate::state) lifetime: f32,
+    pub(in crate::state) speed: f32,
+    /// How far directions stray from `direction`, 0 is a straight jet.
+    pub(in crate::state) spread: f32,
+    /// Billboard radius in clip space at unit depth.
+    pub(in crate::state) size: f32,
+    pub(in crate::state) color: [f32; 4],
+}
+
+fn default_gravity() -> [f32; 3] {
+    [0.0, -9.8, 0.0]
+}
+
+fn default_blend() -> BlendPreset {
+    BlendPreset::Additive
+}
+
+impl ParticleSystemDescriptor {
+    pub(in crate::state) fn from_ron(source: &str) -> Result<Self> {
+        let descriptor: Self = ron::from_str(source)?;
+        if descriptor.emitters.is_empty() {
+            bail!("particle system has no emitters");
+        }
+        for (index, emitter) in descriptor.emitters.iter().enumerate() {
+            if emitter.count == 0 {
+                bail!("emitter {} has a count of 0", index);
+            }
+            if emitter.lifetime <= 0.0 {
+                bail!("emitter {} has a non-positive lifetime", index);
+            }
+        }
+        descriptor.capacity()?;
+        Ok(descriptor)
+    }
+
+    /// Particles of every emitter together.
+    pub(in crate::state) fn capacity(&self) -> Result<u32> {
+        match self.emitters.iter().try_fold(0_u32, |total, emitter| total.checked_add(emitter.count)) {
+            Some(capacity) => Ok(capacity),
+            None => bail!("the emitters have more than {} particles together", u32::MAX),
+        }
+    }
+}
+
+#[wgsl_layout]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(in crate::state) struct Particle {
+    position: [f32; 3],
+    age: f32,
+    velocity: [f32; 3],
+    lifetime: f32,
+    color: [f32; 4],
+    size: f32,
+}
+
+#[wgsl_layout]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(in crate::state) struct Emitter {
+    position: [f32; 3],
+    lifetime: f32,
+    direction: [f32; 3],
+    speed: f32,
+    color: [f32; 4],
+    first: u32,
+    count: u32,
+    spread: f32,
+    size: f32,
+}
+
+#[wgsl_layout]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(in crate::state) struct Simulation {
+    gravity: [f32; 3],
+    drag: f32,
+    delta_time: f32,
+    time: f32,
+    emitter_count: u32,
+    particle_count: u32,
+}
+
+/// Particles simulated by a compute pass and drawn as instanced billboards.
+pub(in crate::state) struct ParticleSystem {
+    simulation: UniformBinding<Simulation>,
+    compute_pipeline: Rc<ComputePipeline>,
+    compute_bind_group: BindGroupBundle,
+    render_pipeline: Rc<RenderPipeline>,
+    render_bind_group: BindGroupBundle,
+}
+
+impl ParticleSystem {
+    /// `uniform_layouts` are the system and camera layouts, bound at groups 0 and 1 while drawing.
+    pub(in crate::state) fn new(device: &Device, cache: &mut PipelineCache, preprocessor: &Preprocessor,
+                                uniform_layouts: [&BindGroupLayout; 2], target: RenderTarget,
+                                desc: &ParticleSystemDescriptor) -> Result<Self> {
+        let mut first = 0;
+        let emitters: Vec<Emitter> = desc.emitters.iter().map(|emitter| {
+            let gpu_emitter = Emitter {
+                position: emitter.position,
+                lifetime: emitter.lifetime,
+                direction: emitter.direction,
+                speed: emitter.speed,
+                color: emitter.color,
+                first,
+                count: emitter.count,
+                spread: emitter.spread,
+                size: emitter.size,
+            };
+            first += emitter.count;
+            gpu_emitter
+        }).collect();
+        let simulation = Simulation {
+            gravity: desc.gravity,
+            drag: desc.drag,
+            delta_time: 0.0,
+            time: 0.0,
+            emitter_count: emitters.len() as u32,
+            particle_count: desc.capacity()?,
+        };
+
+        let simulation = UniformBinding::new(device, cache, simulation);
+        let emitter_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Particle Emitter Buffer"),
+            contents: bytemuck::cast_slice(&emitters),
+            usage: BufferUsages::STORAGE,
+        });
+        // zeroed particles have a lifetime of 0 and respawn on the first update
+        let particle_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Particle Buffer"),
+            size: simulation.uniform().particle_count as BufferAddress * std::mem::size_of::<Particle>() as BufferAddress,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let compute_bind_group = BindGroupBundle::builder("Particle Update Bind Group")
+            .uniform(&simulation)
+            .storage(&particle_buffer, ShaderStages::COMPUTE, false)
+            .storage(&emitter_buffer, ShaderStages::COMPUTE, true)
+            .build(device, cache);
+        let compute_bind_group_layout = compute_bind_group.layout();
+        let compute_key = ComputePipelineKey::new(UPDATE_SHADER.fingerprint(), &[compute_bind_group_layout]);
+        let compute_entries = cache.layout_entries(compute_bind_group_layout).to_vec();
+        let compute_pipeline = cache.compute_pipeline(compute_key, || {
+            let compute_shader = compile_shader(device, preprocessor, &UPDATE_SHADER)?;
+            compute_shader.reflection().validate(&[&compute_entries], &[])?;
+            let compute_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Particle Update Pipeline Layout"),
+                bind_group_layouts: &[compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            Ok(device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("Particle Update Pipeline"),
+                layout: Some(&compute_pipeline_layout),
+                module: compute_shader.module(),
+                entry_point: "cs_main",
+            }))
+        })?;
+
+        let render_bind_group = BindGroupBundle::builder("Particle Render Bind Group")
+            .storage(&particle_buffer, ShaderStages::VERTEX, true)
+            .build(device, cache);
+        let [system_layout, camera_layout] = uniform_layouts;
+        let bind_group_layouts = [system_layout, camera_layout, render_bind_group.layout()];
+        let description = PipelineDescription::translucent(desc.blend);
+        let render_key = RenderPipelineKey::new(RENDER_SHADER.fingerprint(), &[QuadVertex::desc()], &bind_group_layouts, target, description);
+        let layout_entries: Vec<_> = bind_group_layouts.iter().map(|layout| cache.layout_entries(layout).to_vec()).collect();
+        let render_pipeline = cache.render_pipeline(render_key, || {
+            let render_shader = compile_shader(device, preprocessor, &RENDER_SHADER)?;
+            let groups: Vec<_> = layout_entries.iter().map(Vec::as_slice).collect();
+            render_shader.reflection().validate(&groups, &[QuadVertex::desc()])?;
+            let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Particle Render Pipeline Layout"),
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+            Ok(create_pipeline(device, &render_pipeline_layout, target, &description, &[QuadVertex::desc()], &render_shader))
+        })?;
+
+        Ok(Self {
+            simulation,
+            compute_pipeline,
+            compute_bind_group,
+            render_pipeline,
+            render_bind_group,
+        })
+    }
+
+    /// `time` seeds the respawn randomness, `dt` advances the simulation.
+    pub(in crate::state) fn update(&mut self, queue: &Queue, dt: Duration, time: f32) {
+        let simulation = self.simulation.uniform_mut();
+        simulation.delta_time = dt.as_secs_f32();
+        simulation.time = time;
+        self.simulation.flush(queue);
+    }
+
+    pub(in crate::state) fn dispatch(&self, encoder: &mut CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor { label: Some("Particle Update Pass") });
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, self.compute_bind_group.bind_group(), &[]);
+        compute_pass.dispatch_workgroups(self.simulation.uniform().particle_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Draws one billboard per particle with `quad`, expecting the uniforms at groups 0 and 1 to be bound.
+    pub(in crate::state) fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, quad: &'a VertexBinding) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(2, self.render_bind_group.bind_group(), &[]);
+        render_pass.draw_mesh_instanced(0..self.simulation.uniform().particle_count, quad);
+    }
+}
+
+impl Uniform for Simulation {
+    fn get_buffer_label(&self) -> &'static str {
+        "Particle Simulation Buffer"
+    }
+
+    fn get_bind_group_label(&self) -> &'static str {
+        "Particle Simulation Bind Group"
+    }
+
+    fn get_layout_label(&self) -> &'static str {
+        "Particle Simulation Bind Group Layout"
+    }
+
+    fn get_visibility(&self) -> ShaderStages {
+        ShaderStages::COMPUTE
+    }
+}
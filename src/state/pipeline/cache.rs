@@ -0,0 +1,142 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+use anyhow::Result;
+use wgpu::{
+    BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, ComputePipeline, Device,
+    RenderPipeline, VertexBufferLayout
+};
+use super::{PipelineDescription, RenderTarget};
+
+// bind group layouts handed out by the cache live as long as it does, so their addresses identify them
+fn layout_ids(layouts: &[&BindGroupLayout]) -> Vec<usize> {
+    layouts.iter().map(|layout| *layout as *const BindGroupLayout as usize).collect()
+}
+
+/// Everything a render pipeline is created from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(in crate::state) struct RenderPipelineKey {
+    shader: u64,
+    vertex_layouts: Vec<VertexBufferLayout<'static>>,
+    bind_group_layouts: Vec<usize>,
+    target: RenderTarget,
+    description: PipelineDescription,
+}
+
+impl RenderPipelineKey {
+    /// `bind_group_layouts` must come from [`PipelineCache::bind_group_layout`].
+    pub(in crate::state) fn new(shader: u64, vertex_layouts: &[VertexBufferLayout<'static>], bind_group_layouts: &[&BindGroupLayout],
+                                target: RenderTarget, description: PipelineDescription) -> Self {
+        Self {
+            shader,
+            vertex_layouts: vertex_layouts.to_vec(),
+            bind_group_layouts: layout_ids(bind_group_layouts),
+            target,
+            description,
+        }
+    }
+}
+
+/// Everything a compute pipeline is created from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(in crate::state) struct ComputePipelineKey {
+    shader: u64,
+    bind_group_layouts: Vec<usize>,
+}
+
+impl ComputePipelineKey {
+    /// `bind_group_layouts` must come from [`PipelineCache::bind_group_layout`].
+    pub(in crate::state) fn new(shader: u64, bind_group_layouts: &[&BindGroupLayout]) -> Self {
+        Self {
+            shader,
+            bind_group_layouts: layout_ids(bind_group_layouts),
+        }
+    }
+}
+
+/// How well the pipelines of a device are reused, see [`Renderer::pipeline_stats`](crate::Renderer::pipeline_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Pipelines found in the cache instead of being compiled.
+    pub hits: u64,
+    /// Pipelines compiled because the cache had none like them.
+    pub misses: u64,
+    /// Render pipelines in the cache.
+    pub render_pipelines: usize,
+    /// Compute pipelines in the cache.
+    pub compute_pipelines: usize,
+}
+
+impl CacheStats {
+    /// Render and compute pipelines in the cache.
+    pub fn pipelines(&self) -> usize {
+        self.render_pipelines + self.compute_pipelines
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} render and {} compute pipelines cached, {} hits, {} misses",
+               self.render_pipelines, self.compute_pipelines, self.hits, self.misses)
+    }
+}
+
+/// Pipelines and bind group layouts of one device, created once and shared afterwards.
+#[derive(Debug, Default)]
+pub(in crate::state) struct PipelineCache {
+    bind_group_layouts: HashMap<Vec<BindGroupLayoutEntry>, Rc<BindGroupLayout>>,
+    render_pipelines: HashMap<RenderPipelineKey, Rc<RenderPipeline>>,
+    compute_pipelines: HashMap<ComputePipelineKey, Rc<ComputePipeline>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl PipelineCache {
+    /// Layouts with the same entries are shared, whatever their label.
+    pub(in crate::state) fn bind_group_layout(&mut self, device: &Device, desc: &BindGroupLayoutDescriptor) -> Rc<BindGroupLayout> {
+        self.bind_group_layouts.entry(desc.entries.to_vec())
+            .or_insert_with(|| Rc::new(device.create_bind_group_layout(desc)))
+            .clone()
+    }
+
+    /// Entries `layout` was created from. Panics if it didn't come from [`PipelineCache::bind_group_layout`].
+    pub(in crate::state) fn layout_entries(&self, layout: &BindGroupLayout) -> &[BindGroupLayoutEntry] {
+        self.bind_group_layouts.iter()
+            .find(|(_, cached)| std::ptr::eq(Rc::as_ptr(cached), layout))
+            .map(|(entries, _)| entries.as_slice())
+            .expect("bind group layout wasn't created by this cache")
+    }
+
+    /// Returns the pipeline for `key`, calling `create` to compile it on a miss.
+    pub(in crate::state) fn render_pipeline(&mut self, key: RenderPipelineKey,
+                                            create: impl FnOnce() -> Result<RenderPipeline>) -> Result<Rc<RenderPipeline>> {
+        if let Some(pipeline) = self.render_pipelines.get(&key) {
+            self.hits += 1;
+            return Ok(pipeline.clone());
+        }
+        let pipeline = Rc::new(create()?);
+        self.misses += 1;
+        self.render_pipelines.insert(key, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Returns the pipeline for `key`, calling `create` to compile it on a miss.
+    pub(in crate::state) fn compute_pipeline(&mut self, key: ComputePipelineKey,
+                                             create: impl FnOnce() -> Result<ComputePipeline>) -> Result<Rc<ComputePipeline>> {
+        if let Some(pipeline) = self.compute_pipelines.get(&key) {
+            self.hits += 1;
+            return Ok(pipeline.clone());
+        }
+        let pipeline = Rc::new(create()?);
+        self.misses += 1;
+        self.compute_pipelines.insert(key, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    pub(in crate::state) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            render_pipelines: self.render_pipelines.len(),
+            compute_pipelines: self.compute_pipelines.len(),
+        }
+    }
+}
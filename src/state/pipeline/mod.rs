@@ -0,0 +1,147 @@
+pub(super) mod cache;
+
+use serde::Deserialize;
+use wgpu::{
+    BlendComponent, BlendFactor, BlendOperation, BlendState, ColorTargetState, ColorWrites,
+    CompareFunction, DepthBiasState, DepthStencilState, Device, Face, Features, FrontFace,
+    IndexFormat, MultisampleState, PipelineLayout, PolygonMode, PrimitiveState, PrimitiveTopology,
+    RenderPipeline, RenderPipelineDescriptor, StencilState, TextureFormat, VertexBufferLayout
+};
+use crate::state::shader::Shader;
+
+/// Formats and sample count of the attachments a pipeline draws into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(in crate::state) struct RenderTarget {
+    pub(in crate::state) color_format: TextureFormat,
+    pub(in crate::state) depth_format: Option<TextureFormat>,
+    pub(in crate::state) sample_count: u32,
+}
+
+/// How fragments are combined with what is already in the color target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub(in crate::state) enum BlendPreset {
+    /// Overwrites the target, for opaque geometry.
+    Replace,
+    /// Classic `src * a + dst * (1 - a)` for straight alpha.
+    Alpha,
+    /// Adds `src * a` to the target, for glows and particles.
+    Additive,
+    /// Like `Alpha`, for colors already multiplied by their alpha.
+    Premultiplied,
+}
+
+impl BlendPreset {
+    fn state(self) -> BlendState {
+        match self {
+            Self::Replace => BlendState::REPLACE,
+            Self::Alpha => BlendState::ALPHA_BLENDING,
+            Self::Additive => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            },
+            Self::Premultiplied => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        }
+    }
+}
+
+/// Fixed-function state of a render pipeline, everything besides shaders, layouts and targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(in crate::state) struct PipelineDescription {
+    pub(in crate::state) blend: BlendPreset,
+    pub(in crate::state) cull_mode: Option<Face>,
+    pub(in crate::state) topology: PrimitiveTopology,
+    /// Format of the index buffers drawn with, which strips need to know their restart value.
+    pub(in crate::state) index_format: IndexFormat,
+    /// Draws edges only, ignored without `Features::POLYGON_MODE_LINE`.
+    pub(in crate::state) wireframe: bool,
+    /// Translucent geometry usually tests against depth without writing it.
+    pub(in crate::state) depth_write: bool,
+}
+
+impl PipelineDescription {
+    pub(in crate::state) const OPAQUE: Self = Self {
+        blend: BlendPreset::Replace,
+        cull_mode: Some(Face::Back),
+        topology: PrimitiveTopology::TriangleList,
+        // the quad is indexed with u16
+        index_format: IndexFormat::Uint16,
+        wireframe: false,
+        depth_write: true,
+    };
+
+    /// Blended, double sided and leaving the depth buffer alone.
+    pub(in crate::state) const fn translucent(blend: BlendPreset) -> Self {
+        Self {
+            blend,
+            cull_mode: None,
+            depth_write: false,
+            ..Self::OPAQUE
+        }
+    }
+}
+
+impl Default for PipelineDescription {
+    fn default() -> Self {
+        Self::OPAQUE
+    }
+}
+
+pub(in crate::state) fn create_pipeline(device: &Device, layout: &PipelineLayout, target: RenderTarget, description: &PipelineDescription,
+                                        vertex_layouts: &[VertexBufferLayout], shader: &Shader) -> RenderPipeline {
+    let polygon_mode = if !description.wireframe {
+        PolygonMode::Fill
+    } else if device.features().contains(Features::POLYGON_MODE_LINE) {
+        PolygonMode::Line
+    } else {
+        log::warn!("Wireframe needs POLYGON_MODE_LINE, which this device lacks, drawing filled instead");
+        PolygonMode::Fill
+    };
+
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        // vertex shader and buffers
+        vertex: shader.vertex_state(vertex_layouts),
+        // fragment shader and buffers and blending modes
+        fragment: Some(shader.fragment_state(&[Some(ColorTargetState {
+            format: target.color_format,
+            blend: Some(description.blend.state()),
+            write_mask: ColorWrites::ALL,
+        })])),
+        // how to interpret vertices as triangles
+        primitive: PrimitiveState {
+            topology: description.topology,
+            strip_index_format: description.topology.is_strip().then_some(description.index_format),
+            front_face: FrontFace::Ccw,
+            cull_mode: description.cull_mode,
+            polygon_mode,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: target.depth_format.map(|format| DepthStencilState {
+            format,
+            depth_write_enabled: description.depth_write,
+            // the fullscreen quad sits on the far plane and must pass against the cleared depth
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState {
+            count: target.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        // If the pipeline will be used with a multiview render pass, this indicates how many array layers the attachments will have.
+        multiview: None,
+    })
+}
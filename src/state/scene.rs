@@ -0,0 +1,888 @@
+use std::{cell::RefCell, iter, rc::Rc};
+use anyhow::{bail, Result};
+use bytemuck::Pod;
+use image::DynamicImage;
+use wgpu::{*, util::{BufferInitDescriptor, DeviceExt}};
+use winit::{dpi::{PhysicalPosition, PhysicalSize}, event::*};
+use super::{
+    camera::{Camera, CameraSettings, Projection, controller::CameraController},
+    compare::{compare_pipeline, CompareMode, CompareTargets},
+    error::InitError,
+    gpu::Capabilities,
+    compute::ComputePass,
+    geometry::{Vertex, VertexBinding, model::{model_pipeline, ModelDescriptor, PlacedModel}, quad::{QuadVertex, DrawQuad}},
+    pipeline::{create_pipeline, PipelineDescription, RenderTarget, cache::{CacheStats, PipelineCache, RenderPipelineKey}},
+    texture::{Texture, readback::Readback},
+    particle::{Particle, ParticleSystem, ParticleSystemDescriptor, FOUNTAIN, WORKGROUP_SIZE},
+    shader::{compile_shader, inputs::inputs_wgsl, library::{ShaderLibrary, ShaderProgram}, preprocessor::Preprocessor},
+    time::Duration,
+    uniform::{self, Flush, Uniform, UniformBinding, bundle::{BindGroupBundle, BundleBuilder}, dynamic::DynamicUniformBuffer,
+              camera::CameraUniform, compare::CompareUniform, light::{LightDescriptor, LightUniform}, params::Params, system::SystemUniform}
+};
+
+/// What a scene draws besides its shaders and particles, kept on the CPU to rebuild it on a new device.
+#[derive(Debug, Clone)]
+pub(super) struct SceneContent {
+    pub(super) clear_color: [f32; 4],
+    pub(super) params: Params,
+    pub(super) models: Vec<ModelDescriptor>,
+    pub(super) lights: Vec<LightDescriptor>,
+}
+
+impl Default for SceneContent {
+    fn default() -> Self {
+        Self {
+            clear_color: [0.1, 0.2, 0.3, 1.0],
+            params: Params::default(),
+            models: Vec::new(),
+            lights: Vec::new(),
+        }
+    }
+}
+
+// the parameters and channel textures a shader reads from the group `inputs.wgsl` declares
+struct ShaderInputs {
+    bind_group: BindGroupBundle,
+    _channels: Vec<Texture>,
+}
+
+// GPU resources of a program, see `build_program`
+struct Program {
+    render_pipeline: Rc<RenderPipeline>,
+    compute: Option<ComputePass>,
+    inputs: Option<ShaderInputs>,
+}
+
+// the shader drawn next to the current one and what combines the two, see `Scene::compare`
+struct Comparison {
+    name: String,
+    program: Program,
+    targets: CompareTargets,
+    uniform: UniformBinding<CompareUniform>,
+    pipeline: Rc<RenderPipeline>,
+}
+
+// uploads the channels of `program` and groups them with the parameters
+fn build_inputs(device: &Device, queue: &Queue, cache: &mut PipelineCache, params: Option<&Buffer>, program: &ShaderProgram) -> Option<ShaderInputs> {
+    if params.is_none() && program.channels.is_empty() {
+        return None;
+    }
+    let channels: Vec<_> = program.channels.iter().enumerate().map(|(index, image)| {
+        Texture::builder(&format!("Channel {}", index))
+            .address_mode(AddressMode::Repeat)
+            .filter(FilterMode::Linear)
+            .from_image(device, queue, &DynamicImage::ImageRgba8(image.as_ref().clone()))
+    }).collect();
+
+    let mut builder = BindGroupBundle::builder("Shader Inputs Bind Group");
+    if let Some(params) = params {
+        builder = builder.uniform_buffer(params, ShaderStages::FRAGMENT);
+    }
+    for channel in &channels {
+        builder = builder.texture(channel, ShaderStages::FRAGMENT);
+    }
+    Some(ShaderInputs { bind_group: builder.build(device, cache), _channels: channels })
+}
+
+// compiles `program` into a render pipeline over the quad and, when it has one, the compute pass feeding it,
+// reusing pipelines compiled before
+#[allow(clippy::too_many_arguments)]
+fn build_program(device: &Device, queue: &Queue, cache: &mut PipelineCache, preprocessor: &Preprocessor, uniform_layouts: [&BindGroupLayout; 2],
+                 target: RenderTarget, compute_supported: bool, params: (&Params, Option<&Buffer>), program: &ShaderProgram) -> Result<Program> {
+    let compute = match &program.compute {
+        Some(_) if !compute_supported => bail!("shader needs compute shaders, which this adapter doesn't support"),
+        Some(desc) => Some(ComputePass::new(device, cache, preprocessor, uniform_layouts, desc)?),
+        None => None,
+    };
+
+    // the compute results are bound right after the uniforms, the inputs after them
+    let mut bind_group_layouts = uniform_layouts.to_vec();
+    if let Some(compute) = &compute {
+        bind_group_layouts.push(compute.render_bind_group_layout());
+    }
+    let preprocessor = preprocessor.with_source("inputs.wgsl", inputs_wgsl(bind_group_layouts.len() as u32, params.0, program.channels.len()));
+    let inputs = build_inputs(device, queue, cache, params.1, program);
+    if let Some(inputs) = &inputs {
+        bind_group_layouts.push(inputs.bind_group.layout());
+    }
+    let description = PipelineDescription::OPAQUE;
+    // `inputs.wgsl` differs between scenes whose layouts look alike, e.g. params of other shapes
+    let key = RenderPipelineKey::new(program.render.processed_fingerprint(&preprocessor)?, &[QuadVertex::desc()], &bind_group_layouts, target, description);
+    let layout_entries: Vec<_> = bind_group_layouts.iter().map(|layout| cache.layout_entries(layout).to_vec()).collect();
+    let render_pipeline = cache.render_pipeline(key, || {
+        let shader = compile_shader(device, &preprocessor, &program.render)?;
+        if !shader.has_fragment() {
+            bail!("shader has no fragment entry point");
+        }
+        let groups: Vec<_> = layout_entries.iter().map(Vec::as_slice).collect();
+        shader.reflection().validate(&groups, &[QuadVertex::desc()])?;
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        Ok(create_pipeline(device, &render_pipeline_layout, target, &description, &[QuadVertex::desc()], &shader))
+    })?;
+
+    Ok(Program { render_pipeline, compute, inputs })
+}
+
+/// The shader effects, particles, camera and clock, drawn into color targets of any device.
+///
+/// A [`Renderer`](crate::Renderer) draws its scene into a window. To draw into a texture or render pass
+/// of a device owned elsewhere, create one with [`RendererBuilder::build_scene`](crate::RendererBuilder::build_scene)
+/// and pass the device and queue to the methods needing them.
+pub struct Scene {
+    target: RenderTarget,
+    size: PhysicalSize<u32>,
+    msaa_framebuffer: Option<TextureView>,
+    depth_texture: Texture,
+    program: Program,
+    comparison: Option<Comparison>,
+    compare_mode: CompareMode,
+    divider: f32,
+    dragging_divider: bool,
+    // shared with the scenes of other windows on the same device
+    pipelines: Rc<RefCell<PipelineCache>>,
+    capabilities: Capabilities,
+    particles: Option<ParticleSystem>,
+    particle_definitions: String,
+    preprocessor: Preprocessor,
+    library: ShaderLibrary,
+    content: SceneContent,
+    params_buffer: Option<Buffer>,
+    params_dirty: bool,
+    models: Vec<PlacedModel>,
+    // one for every model, those drawn alike share theirs through the cache
+    model_pipelines: Vec<Rc<RenderPipeline>>,
+    // draws every model as a wireframe
+    wireframe: bool,
+    mesh: VertexBinding,
+    // advanced by every update, so frames are reproducible for a given series of steps
+    time: Duration,
+    frame: u32,
+    mouse_lock: bool,
+    cursor_position: PhysicalPosition<f64>,
+    camera: Camera,
+    projection: Projection,
+    camera_controller: CameraController,
+    system_uniform: UniformBinding<SystemUniform>,
+    camera_uniform: UniformBinding<CameraUniform>,
+    light_uniform: UniformBinding<LightUniform>,
+}
+
+impl Scene {
+    // creates every GPU resource on `device` from the CPU-side descriptions
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn new(device: &Device, queue: &Queue, capabilities: Capabilities, shared_pipelines: Rc<RefCell<PipelineCache>>, mut library: ShaderLibrary,
+                      shader: &str, particles: Option<&str>, content: SceneContent, target: RenderTarget, size: PhysicalSize<u32>) -> Result<Self, InitError> {
+        let (msaa_framebuffer, depth_texture) = Self::create_framebuffers(device, target, size);
+
+        let mut pipelines = shared_pipelines.borrow_mut();
+        let system_uniform = UniformBinding::new(device, &mut pipelines, SystemUniform::new(size));
+        let camera_uniform = UniformBinding::new(device, &mut pipelines, CameraUniform::new());
+        let light_uniform = UniformBinding::new(device, &mut pipelines, LightUniform::new(&content.lights));
+        let params_buffer = (!content.params.is_empty()).then(|| device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Params Buffer"),
+            contents: content.params.bytes(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        }));
+
+        let preprocessor = Preprocessor::new();
+        library.set_current(shader);
+        let program = build_program(device, queue, &mut pipelines, &preprocessor, [system_uniform.bind_group_layout(), camera_uniform.bind_group_layout()],
+                                    target, capabilities.compute_shaders, (&content.params, params_buffer.as_ref()), library.get(library.current()).unwrap())
+            .map_err(InitError::Shader)?;
+
+        let models: Vec<_> = content.models.iter().map(|model| PlacedModel::new(device, queue, &mut pipelines, model)).collect();
+        let model_pipelines = Self::build_model_pipelines(device, &mut pipelines, &preprocessor, &models,
+                                                          [camera_uniform.bind_group_layout(), light_uniform.bind_group_layout()], target, false)
+            .map_err(|error| InitError::Shader(error.context("model shader")))?;
+
+        let particle_definitions = particles.unwrap_or(FOUNTAIN).to_string();
+        let particles = particles.and_then(|_| {
+            Self::build_particles(device, &mut pipelines, &preprocessor, [system_uniform.bind_group_layout(), camera_uniform.bind_group_layout()],
+                                  target, capabilities, &particle_definitions)
+                .map_err(|error| log::error!("Couldn't create particle system: {:#}", error))
+                .ok()
+        });
+
+        let mesh = QuadVertex::new().make_binding(device);
+        drop(pipelines);
+
+        let (camera, projection, camera_controller) = CameraSettings::default().create(size.width, size.height);
+
+        Ok(Self {
+            target, size, msaa_framebuffer, depth_texture, program, comparison: None, compare_mode: CompareMode::default(), divider: 0.5,
+            dragging_divider: false, pipelines: shared_pipelines, capabilities, particles, particle_definitions, preprocessor, library,
+            content, params_buffer, params_dirty: false, models, model_pipelines, wireframe: false,
+            mesh, time: Duration::ZERO, frame: 0, mouse_lock: false,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            camera, projection, camera_controller, system_uniform, camera_uniform, light_uniform
+        })
+    }
+
+    // the multisampled color target, when multisampling, and a depth buffer matching it
+    fn create_framebuffers(device: &Device, target: RenderTarget, size: PhysicalSize<u32>) -> (Option<TextureView>, Texture) {
+        let size = (size.width, size.height);
+        let msaa_framebuffer = (target.sample_count > 1)
+            .then(|| Texture::create_multisampled_framebuffer(device, size, target.color_format, target.sample_count));
+        let depth_texture = Texture::create_depth_texture(device, size, target.sample_count, "Depth Texture");
+        (msaa_framebuffer, depth_texture)
+    }
+
+    /// Recreates pipelines, uniforms, meshes and textures on a new device, keeping the
+    /// loaded shaders, particle definitions, camera and clock.
+    pub(super) fn rebuild(&mut self, device: &Device, queue: &Queue, capabilities: Capabilities, pipelines: Rc<RefCell<PipelineCache>>,
+                          target: RenderTarget) -> Result<(), InitError> {
+        // built from copies, so a failure leaves the shaders, models, lights and parameters for another attempt
+        let shader = self.library.current().to_string();
+        let particles = self.particles.is_some().then_some(self.particle_definitions.as_str());
+        let mut scene = Self::new(device, queue, capabilities, pipelines, self.library.clone(), &shader, particles, self.content.clone(),
+                                  target, self.size)?;
+
+        scene.particle_definitions = std::mem::take(&mut self.particle_definitions);
+        scene.time = self.time;
+        scene.frame = self.frame;
+        scene.mouse_lock = self.mouse_lock;
+        scene.cursor_position = self.cursor_position;
+        std::mem::swap(&mut scene.camera, &mut self.camera);
+        std::mem::swap(&mut scene.projection, &mut self.projection);
+        std::mem::swap(&mut scene.camera_controller, &mut self.camera_controller);
+        (scene.compare_mode, scene.divider) = (self.compare_mode, self.divider);
+        if self.wireframe {
+            scene.toggle_wireframe(device);
+        }
+        if let Some(comparison) = &self.comparison {
+            if let Err(error) = scene.compare(device, queue, &comparison.name) {
+                log::error!("Couldn't compare with shader `{}` again: {:#}", comparison.name, error);
+            }
+        }
+        *self = scene;
+        Ok(())
+    }
+
+    /// Format and sample count pipelines are built for, rebuilding them when either changed.
+    pub(super) fn set_target(&mut self, device: &Device, queue: &Queue, target: RenderTarget) {
+        if target == self.target {
+            return;
+        }
+        self.target = target;
+        (self.msaa_framebuffer, self.depth_texture) = Self::create_framebuffers(device, self.target, self.size);
+
+        let current = self.library.current().to_string();
+        if let Err(error) = self.select_shader(device, queue, &current) {
+            log::error!("Couldn't rebuild shader `{}`: {:#}", current, error);
+        }
+        if let Some(compared) = self.compared_shader().map(str::to_string) {
+            if let Err(error) = self.compare(device, queue, &compared) {
+                log::error!("Couldn't rebuild shader `{}`: {:#}", compared, error);
+                self.stop_comparing();
+            }
+        }
+        if self.particles.is_some() {
+            self.particles = None;
+            self.toggle_particles(device);
+        }
+        self.rebuild_model_pipelines(device);
+    }
+
+    // a pipeline for every model in `models`, as wireframes when `wireframe` is set
+    fn build_model_pipelines(device: &Device, cache: &mut PipelineCache, preprocessor: &Preprocessor, models: &[PlacedModel],
+                             uniform_layouts: [&BindGroupLayout; 2], target: RenderTarget, wireframe: bool) -> Result<Vec<Rc<RenderPipeline>>> {
+        let [camera_layout, light_layout] = uniform_layouts;
+        models.iter().map(|model| {
+            let description = model.description();
+            let description = PipelineDescription { wireframe: description.wireframe || wireframe, ..description };
+            model_pipeline(device, cache, preprocessor, [model.material_layout(), camera_layout, light_layout], target, description)
+        }).collect()
+    }
+
+    fn rebuild_model_pipelines(&mut self, device: &Device) {
+        match Self::build_model_pipelines(device, &mut self.pipelines.borrow_mut(), &self.preprocessor, &self.models,
+                                          [self.camera_uniform.bind_group_layout(), self.light_uniform.bind_group_layout()], self.target, self.wireframe) {
+            Ok(pipelines) => self.model_pipelines = pipelines,
+            Err(error) => log::error!("Couldn't rebuild the model shader: {:#}", error),
+        }
+    }
+
+    /// Whether every model is drawn as a wireframe, whatever the scene file says.
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    /// Draws every model as a wireframe, or as the scene file describes it again.
+    /// Wireframes need `Features::POLYGON_MODE_LINE`, without it models stay filled.
+    pub fn toggle_wireframe(&mut self, device: &Device) {
+        self.wireframe = !self.wireframe;
+        self.rebuild_model_pipelines(device);
+    }
+
+    /// How often pipelines were reused rather than compiled, by this scene and every one sharing its cache.
+    pub fn pipeline_stats(&self) -> CacheStats {
+        self.pipelines.borrow().stats()
+    }
+
+    /// Registers `program` under `name`, replacing any shader called the same, without selecting it.
+    pub fn add_shader(&mut self, name: &str, program: ShaderProgram) {
+        self.library.insert(name, program);
+    }
+
+    /// Reads a `.wgsl`, `.glsl`/`.frag` or `.spv` file and registers it under its file stem, which is returned.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_shader(&mut self, path: &std::path::Path) -> Result<String> {
+        self.library.load(path)
+    }
+
+    /// Every registered shader name, in cycling order.
+    pub fn shader_names(&self) -> impl Iterator<Item = &str> {
+        self.library.names()
+    }
+
+    /// Name of the shader being drawn.
+    pub fn current_shader(&self) -> &str {
+        self.library.current()
+    }
+
+    /// Compiles the shader called `name`, or reuses its pipelines, and draws with it from the next frame.
+    pub fn select_shader(&mut self, device: &Device, queue: &Queue, name: &str) -> Result<()> {
+        let program = match self.library.get(name) {
+            Some(program) => program,
+            None => bail!("unknown shader `{}`, available: {}", name, self.library.names().collect::<Vec<_>>().join(", ")),
+        };
+        self.program = build_program(device, queue, &mut self.pipelines.borrow_mut(), &self.preprocessor,
+                                     [self.system_uniform.bind_group_layout(), self.camera_uniform.bind_group_layout()],
+                                     self.target, self.capabilities.compute_shaders, (&self.content.params, self.params_buffer.as_ref()), program)?;
+        // restart frame counting so stateful shaders reinitialise
+        self.frame = 0;
+        self.library.set_current(name);
+        log::info!("Selected shader `{}`, {}", name, self.pipelines.borrow().stats());
+        Ok(())
+    }
+
+    fn cycle_shader(&mut self, device: &Device, queue: &Queue) {
+        let next = self.library.next().to_string();
+        if let Err(error) = self.select_shader(device, queue, &next) {
+            log::error!("Couldn't switch to shader `{}`: {:#}", next, error);
+        }
+    }
+
+    /// Draws the shader called `name` next to the current one until [`stop_comparing`](Self::stop_comparing),
+    /// both seeing the same time and frame. How they're shown depends on the [mode](Self::set_compare_mode).
+    pub fn compare(&mut self, device: &Device, queue: &Queue, name: &str) -> Result<()> {
+        let program = match self.library.get(name) {
+            Some(program) => program,
+            None => bail!("unknown shader `{}`, available: {}", name, self.library.names().collect::<Vec<_>>().join(", ")),
+        };
+        let mut pipelines = self.pipelines.borrow_mut();
+        let program = build_program(device, queue, &mut pipelines, &self.preprocessor,
+                                    [self.system_uniform.bind_group_layout(), self.camera_uniform.bind_group_layout()],
+                                    self.target, self.capabilities.compute_shaders, (&self.content.params, self.params_buffer.as_ref()), program)?;
+        let uniform = UniformBinding::new(device, &mut pipelines, CompareUniform::new(self.divider, self.compare_mode));
+        let targets = CompareTargets::new(device, &mut pipelines, self.target, self.size);
+        let pipeline = compare_pipeline(device, &mut pipelines, &self.preprocessor,
+                                        [uniform.bind_group_layout(), targets.bind_group().layout()], self.target)?;
+        drop(pipelines);
+        self.comparison = Some(Comparison { name: name.to_string(), program, targets, uniform, pipeline });
+        log::info!("Comparing shader `{}` with `{}`", self.library.current(), name);
+        Ok(())
+    }
+
+    /// Draws the current shader alone again.
+    pub fn stop_comparing(&mut self) {
+        self.comparison = None;
+        self.dragging_divider = false;
+    }
+
+    /// Name of the shader drawn next to the current one, while comparing.
+    pub fn compared_shader(&self) -> Option<&str> {
+        self.comparison.as_ref().map(|comparison| comparison.name.as_str())
+    }
+
+    /// How the compared shader is shown next to the current one.
+    pub fn compare_mode(&self) -> CompareMode {
+        self.compare_mode
+    }
+
+    /// Shows the compared shader in `mode` from the next frame, and any compared later.
+    pub fn set_compare_mode(&mut self, mode: CompareMode) {
+        self.compare_mode = mode;
+        self.update_comparison();
+    }
+
+    /// Where the compared shader starts in [`CompareMode::Split`], as a fraction of the width.
+    pub fn divider(&self) -> f32 {
+        self.divider
+    }
+
+    /// Moves the divider, clamped to the width.
+    pub fn set_divider(&mut self, divider: f32) {
+        self.divider = divider.clamp(0.0, 1.0);
+        self.update_comparison();
+    }
+
+    fn update_comparison(&mut self) {
+        if let Some(comparison) = &mut self.comparison {
+            comparison.uniform.uniform_mut().update(self.divider, self.compare_mode);
+        }
+    }
+
+    // compares with the shader after the current one, or stops comparing
+    fn toggle_comparison(&mut self, device: &Device, queue: &Queue) {
+        if self.comparison.is_some() {
+            self.stop_comparing();
+            return;
+        }
+        let next = self.library.next().to_string();
+        if let Err(error) = self.compare(device, queue, &next) {
+            log::error!("Couldn't compare with shader `{}`: {:#}", next, error);
+        }
+    }
+
+    fn cycle_compared_shader(&mut self, device: &Device, queue: &Queue) {
+        let Some(compared) = self.compared_shader() else {
+            return;
+        };
+        let next = self.library.after(compared).to_string();
+        if let Err(error) = self.compare(device, queue, &next) {
+            log::error!("Couldn't compare with shader `{}`: {:#}", next, error);
+        }
+    }
+
+    // whether the cursor is close enough to grab the divider
+    fn over_divider(&self) -> bool {
+        let divider = self.divider as f64 * self.size.width as f64;
+        self.comparison.is_some() && self.compare_mode == CompareMode::Split && (self.cursor_position.x - divider).abs() <= 6.0
+    }
+
+    fn build_particles(device: &Device, cache: &mut PipelineCache, preprocessor: &Preprocessor, uniform_layouts: [&BindGroupLayout; 2], target: RenderTarget,
+                       capabilities: Capabilities, definitions: &str) -> Result<ParticleSystem> {
+        if !capabilities.compute_shaders {
+            bail!("particles need compute shaders, which this adapter doesn't support");
+        }
+        if !capabilities.vertex_storage {
+            bail!("particles need storage buffers in vertex shaders, which this adapter doesn't support");
+        }
+        let limits = device.limits();
+        // the update reads the emitters and writes the particles
+        if limits.max_storage_buffers_per_shader_stage < 2 {
+            bail!("particles need 2 storage buffers per shader stage, the device allows {}", limits.max_storage_buffers_per_shader_stage);
+        }
+        let desc = ParticleSystemDescriptor::from_ron(definitions)?;
+        let capacity = desc.capacity()?;
+        let buffer_size = u64::from(capacity) * std::mem::size_of::<Particle>() as u64;
+        if buffer_size > u64::from(limits.max_storage_buffer_binding_size) {
+            bail!("{} particles take {} bytes, over the device's max_storage_buffer_binding_size of {}",
+                  capacity, buffer_size, limits.max_storage_buffer_binding_size);
+        }
+        let workgroups = capacity.div_ceil(WORKGROUP_SIZE);
+        if workgroups > limits.max_compute_workgroups_per_dimension {
+            bail!("{} particles take {} workgroups, over the device's max_compute_workgroups_per_dimension of {}",
+                  capacity, workgroups, limits.max_compute_workgroups_per_dimension);
+        }
+        log::info!("Simulating {} particles from {} emitters", capacity, desc.emitters.len());
+        ParticleSystem::new(device, cache, preprocessor, uniform_layouts, target, &desc)
+    }
+
+    /// Removes the particles, or starts simulating them from the definitions the scene was built with.
+    pub fn toggle_particles(&mut self, device: &Device) {
+        if self.particles.take().is_some() {
+            return;
+        }
+        match Self::build_particles(device, &mut self.pipelines.borrow_mut(), &self.preprocessor, [self.system_uniform.bind_group_layout(), self.camera_uniform.bind_group_layout()],
+                                    self.target, self.capabilities, &self.particle_definitions) {
+            Ok(particles) => self.particles = Some(particles),
+            Err(error) => log::error!("Couldn't create particle system: {:#}", error),
+        }
+    }
+
+    /// Resizes the depth buffer and projection to a target of `width` by `height`, ignoring zero sizes.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.size = PhysicalSize::new(width, height);
+            (self.msaa_framebuffer, self.depth_texture) = Self::create_framebuffers(device, self.target, self.size);
+            if let Some(comparison) = &mut self.comparison {
+                comparison.targets = CompareTargets::new(device, &mut self.pipelines.borrow_mut(), self.target, self.size);
+            }
+            self.projection.resize(width, height);
+        }
+    }
+
+    /// Size of the target in physical pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.size.width, self.size.height)
+    }
+
+    /// Format of the color targets the scene draws into.
+    pub fn format(&self) -> TextureFormat {
+        self.target.color_format
+    }
+
+    /// Turns the camera by raw mouse motion while the left button is held.
+    pub fn handle_mouse_input(&mut self, x: f64, y: f64) {
+        if self.mouse_lock {
+            let _ = &self.camera_controller.process_mouse(x, y);
+        }
+    }
+
+    /// Handles the shader, particle and camera controls, returning whether `event` was consumed.
+    pub fn handle_input(&mut self, device: &Device, queue: &Queue, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                KeyboardInput {
+                    virtual_keycode: Some(VirtualKeyCode::Tab),
+                    state: ElementState::Pressed,
+                    ..
+                },
+                ..
+            } => {
+                self.cycle_shader(device, queue);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                KeyboardInput {
+                    virtual_keycode: Some(VirtualKeyCode::P),
+                    state: ElementState::Pressed,
+                    ..
+                },
+                ..
+            } => {
+                self.toggle_particles(device);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                KeyboardInput {
+                    virtual_keycode: Some(VirtualKeyCode::C),
+                    state: ElementState::Pressed,
+                    ..
+                },
+                ..
+            } => {
+                self.toggle_comparison(device, queue);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                KeyboardInput {
+                    virtual_keycode: Some(VirtualKeyCode::B),
+                    state: ElementState::Pressed,
+                    ..
+                },
+                ..
+            } => {
+                self.cycle_compared_shader(device, queue);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                KeyboardInput {
+                    virtual_keycode: Some(VirtualKeyCode::X),
+                    state: ElementState::Pressed,
+                    ..
+                },
+                ..
+            } => {
+                self.set_compare_mode(match self.compare_mode {
+                    CompareMode::Split => CompareMode::Difference,
+                    CompareMode::Difference => CompareMode::Split,
+                });
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                KeyboardInput {
+                    virtual_keycode: Some(VirtualKeyCode::L),
+                    state: ElementState::Pressed,
+                    ..
+                },
+                ..
+            } => {
+                self.toggle_wireframe(device);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                KeyboardInput {
+                    virtual_keycode: Some(key),
+                    state,
+                    ..
+                },
+                ..
+            } => self.camera_controller.process_keyboard(*key, *state),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                if self.dragging_divider {
+                    self.set_divider((position.x / self.size.width as f64) as f32);
+                }
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.camera_controller.process_scroll(delta);
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => {
+                // grabbing the divider moves it instead of turning the camera
+                let pressed = *state == ElementState::Pressed;
+                self.dragging_divider = pressed && self.over_divider();
+                self.mouse_lock = pressed && !self.dragging_divider;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Time shaders see, in seconds since the scene started unless it was [set](Self::set_time).
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Jumps to `time`, which following updates advance from.
+    pub fn set_time(&mut self, time: Duration) {
+        self.time = time;
+    }
+
+    /// Places the camera at `position`, looking along `yaw` and `pitch` in degrees.
+    /// Pitch is clamped to just short of straight up or down on the next update.
+    pub fn set_camera(&mut self, position: [f32; 3], yaw: f32, pitch: f32) {
+        self.camera = Camera::new(position, cgmath::Deg(yaw), cgmath::Deg(pitch));
+    }
+
+    /// Replaces the camera, its lens and its controls.
+    pub fn set_camera_settings(&mut self, settings: &CameraSettings) {
+        (self.camera, self.projection, self.camera_controller) = settings.create(self.size.width, self.size.height);
+    }
+
+    /// Color the target is cleared to before the shader draws.
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.content.clear_color
+    }
+
+    /// Color the target is cleared to before the shader draws.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.content.clear_color = color;
+    }
+
+    /// Current value of a parameter declared by the scene file.
+    pub fn param(&self, name: &str) -> Option<&[f32]> {
+        self.content.params.get(name)
+    }
+
+    /// Changes a parameter shaders read as `params.<name>`, uploaded by the next update.
+    /// `value` must have as many components as the parameter was declared with.
+    pub fn set_param(&mut self, name: &str, value: &[f32]) -> Result<()> {
+        self.content.params.set(name, value)?;
+        self.params_dirty = true;
+        Ok(())
+    }
+
+    /// Advances the clock, camera and particles by `dt` and uploads the uniforms for the next frame.
+    pub fn update(&mut self, queue: &Queue, dt: Duration) {
+        self.time += dt;
+        self.camera_controller.update_camera(&mut self.camera, dt);
+        self.camera_uniform.uniform_mut().update_view_proj(&self.camera, &self.projection);
+        self.system_uniform.uniform_mut().update_system(self.size, self.time.as_secs_f32(), self.frame);
+        self.system_uniform.uniform_mut().update_mouse(self.cursor_position, self.mouse_lock, self.size);
+        uniform::flush_all(queue, &mut [&mut self.system_uniform, &mut self.camera_uniform]);
+        if let Some(comparison) = &mut self.comparison {
+            comparison.uniform.flush(queue);
+        }
+        if let Some(buffer) = self.params_buffer.as_ref().filter(|_| self.params_dirty) {
+            queue.write_buffer(buffer, 0, self.content.params.bytes());
+            self.params_dirty = false;
+        }
+        if let Some(particles) = &mut self.particles {
+            particles.update(queue, dt, self.time.as_secs_f32());
+        }
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Uploads a new uniform with a bind group of its own, sharing the layout with every uniform of its type.
+    pub fn create_uniform<T: Uniform + Pod>(&mut self, device: &Device, uniform: T) -> UniformBinding<T> {
+        UniformBinding::new(device, &mut self.pipelines.borrow_mut(), uniform)
+    }
+
+    /// Allocates room for `capacity` uniforms bound with dynamic offsets.
+    pub fn create_dynamic_uniforms<T: Uniform + Pod>(&mut self, device: &Device, capacity: usize) -> DynamicUniformBuffer<T> {
+        DynamicUniformBuffer::new(device, &mut self.pipelines.borrow_mut(), capacity)
+    }
+
+    /// Creates the bind group `builder` describes, sharing its layout with every group of the same entries.
+    pub fn create_bind_group(&mut self, device: &Device, builder: BundleBuilder) -> BindGroupBundle {
+        builder.build(device, &mut self.pipelines.borrow_mut())
+    }
+
+    /// Uploads every changed uniform in `uniforms`, returning how many were written.
+    pub fn flush(&self, queue: &Queue, uniforms: &mut [&mut dyn Flush]) -> usize {
+        uniform::flush_all(queue, uniforms)
+    }
+
+    /// Runs the compute passes feeding the shader and particles and, while comparing, draws both shaders,
+    /// which [`draw`](Self::draw) needs to have been recorded first.
+    pub fn prepare(&self, encoder: &mut CommandEncoder) {
+        let compared = self.comparison.as_ref().map(|comparison| &comparison.program);
+        for compute in iter::once(&self.program).chain(compared).filter_map(|program| program.compute.as_ref()) {
+            encoder.push_debug_group("compute pass");
+            compute.dispatch(encoder, [self.system_uniform.bind_group(), self.camera_uniform.bind_group()]);
+            encoder.pop_debug_group();
+        }
+        if let Some(particles) = &self.particles {
+            encoder.push_debug_group("particle update");
+            particles.dispatch(encoder);
+            encoder.pop_debug_group();
+        }
+        self.record_comparison(encoder);
+    }
+
+    // both shaders into the targets the compare pipeline combines
+    fn record_comparison(&self, encoder: &mut CommandEncoder) {
+        let Some(comparison) = &self.comparison else {
+            return;
+        };
+        encoder.push_debug_group("comparison passes");
+        let targets = &comparison.targets;
+        for (program, view) in [(&self.program, targets.current_view()), (&comparison.program, targets.compared_view())] {
+            let mut render_pass = self.begin_render_pass(encoder, view, "Compare Pass");
+            self.draw_program(&mut render_pass, program);
+        }
+        encoder.pop_debug_group();
+    }
+
+    /// Depth buffer [`draw`](Self::draw) expects as the depth attachment of a render pass.
+    pub fn depth_view(&self) -> &TextureView {
+        &self.depth_texture.view
+    }
+
+    /// Draws the shader, models and particles into a render pass of the scene's format and sample count,
+    /// whose depth attachment is [`depth_view`](Self::depth_view).
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        match &self.comparison {
+            Some(comparison) => {
+                render_pass.set_pipeline(&comparison.pipeline);
+                render_pass.set_bind_group(0, comparison.uniform.bind_group(), &[]);
+                render_pass.set_bind_group(1, comparison.targets.bind_group().bind_group(), &[]);
+                render_pass.draw_mesh(&self.mesh);
+            }
+            None => self.draw_program(render_pass, &self.program),
+        }
+
+        // opaque models go before the particles, which don't write depth
+        for (model, pipeline) in self.models.iter().zip(&self.model_pipelines) {
+            render_pass.set_pipeline(pipeline);
+            model.draw(render_pass, self.camera_uniform.bind_group(), self.light_uniform.bind_group());
+        }
+        if let Some(particles) = &self.particles {
+            if !self.models.is_empty() || self.comparison.is_some() {
+                render_pass.set_bind_group(0, self.system_uniform.bind_group(), &[]);
+                render_pass.set_bind_group(1, self.camera_uniform.bind_group(), &[]);
+            }
+            particles.draw(render_pass, &self.mesh);
+        }
+    }
+
+    // the quad drawn with `program`
+    fn draw_program<'a>(&'a self, render_pass: &mut RenderPass<'a>, program: &'a Program) {
+        render_pass.set_pipeline(&program.render_pipeline);
+        render_pass.set_bind_group(0, self.system_uniform.bind_group(), &[]);
+        render_pass.set_bind_group(1, self.camera_uniform.bind_group(), &[]);
+        let mut group = 2;
+        if let Some(compute) = &program.compute {
+            render_pass.set_bind_group(group, compute.render_bind_group(), &[]);
+            group += 1;
+        }
+        if let Some(inputs) = &program.inputs {
+            render_pass.set_bind_group(group, inputs.bind_group.bind_group(), &[]);
+        }
+        render_pass.draw_mesh(&self.mesh);
+    }
+
+    fn clear_color_value(&self) -> Color {
+        let [r, g, b, a] = self.content.clear_color.map(f64::from);
+        Color { r, g, b, a }
+    }
+
+    /// Records the compute passes and a render pass clearing and drawing into `view`,
+    /// which must be as large as the scene and of its format.
+    pub fn record(&self, encoder: &mut CommandEncoder, view: &TextureView) {
+        self.prepare(encoder);
+
+        encoder.push_debug_group("rendering passes");
+        {
+            let mut render_pass = self.begin_render_pass(encoder, view, "Render Pass");
+            self.draw(&mut render_pass);
+        }
+        encoder.pop_debug_group();
+    }
+
+    /// Draws the last frame again at `scale` times the size and starts reading it back. Compute passes and
+    /// particles aren't advanced, so the frame matches the one [`record`](Self::record) drew.
+    pub(in crate::state) fn capture(&mut self, device: &Device, queue: &Queue, scale: u32) -> Result<Readback> {
+        let size = self.size;
+        let (width, height) = (size.width.saturating_mul(scale), size.height.saturating_mul(scale));
+        let max = device.limits().max_texture_dimension_2d;
+        if scale == 0 || width > max || height > max {
+            bail!("can't capture {}x{} pixels, textures are at most {} wide and high", width, height, max);
+        }
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Capture Target"),
+            size: Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.target.color_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        let last = *self.system_uniform.uniform();
+        if scale > 1 {
+            self.resize(device, width, height);
+            *self.system_uniform.uniform_mut() = last.scaled(scale);
+            self.system_uniform.flush(queue);
+        }
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: Some("Capture Encoder") });
+        self.record_comparison(&mut encoder);
+        {
+            let mut render_pass = self.begin_render_pass(&mut encoder, &view, "Capture Pass");
+            self.draw(&mut render_pass);
+        }
+        // the scaled uniform has to be used before it's restored
+        queue.submit(iter::once(encoder.finish()));
+        if scale > 1 {
+            self.resize(device, size.width, size.height);
+            *self.system_uniform.uniform_mut() = last;
+            self.system_uniform.flush(queue);
+        }
+        Readback::new(device, queue, &texture, self.target.color_format, (width, height))
+    }
+
+    // a pass clearing `view` and the depth buffer
+    fn begin_render_pass<'a>(&'a self, encoder: &'a mut CommandEncoder, view: &'a TextureView, label: &str) -> RenderPass<'a> {
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some(label),
+            // when multisampling, draw into the multisampled target and resolve into the view
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: self.msaa_framebuffer.as_ref().unwrap_or(view),
+                resolve_target: self.msaa_framebuffer.as_ref().map(|_| view),
+                ops: Operations {
+                    load: LoadOp::Clear(self.clear_color_value()),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        })
+    }
+}
@@ -0,0 +1,398 @@
+use std::{collections::BTreeMap, fmt::Display, path::Path, sync::Arc};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, de::DeserializeOwned};
+use wgpu::{Face, PrimitiveTopology};
+use super::{
+    camera::CameraSettings,
+    compute::ComputeDescriptor,
+    geometry::model::{ModelData, ModelDescriptor, Placement},
+    particle::ParticleSystemDescriptor,
+    pipeline::PipelineDescription,
+    scene::SceneContent,
+    shader::{ShaderCode, inputs::MAX_CHANNELS, library::ShaderProgram},
+    uniform::{light::{LightDescriptor, MAX_LIGHTS}, params::Params}
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Syntax of a scene file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneFormat {
+    /// [TOML](https://toml.io), as in the example of [`SceneFile`].
+    Toml,
+    /// JSON, with the same keys as TOML.
+    Json,
+    /// [RON](https://github.com/ron-rs/ron), with the same keys as TOML.
+    Ron,
+}
+
+impl SceneFormat {
+    /// The format of a `toml`, `json` or `ron` file.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "ron" => Some(Self::Ron),
+            _ => None,
+        }
+    }
+}
+
+// the file as written, before anything it names is read
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawScene {
+    shader: Option<String>,
+    #[serde(default)]
+    shaders: BTreeMap<String, RawShader>,
+    #[serde(default)]
+    camera: CameraSettings,
+    clear_color: Option<[f32; 4]>,
+    particles: Option<String>,
+    #[serde(default)]
+    models: Vec<RawModel>,
+    #[serde(default)]
+    lights: Vec<LightDescriptor>,
+    #[serde(default)]
+    params: BTreeMap<String, RawParam>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawShader {
+    path: String,
+    #[serde(default)]
+    channels: Vec<String>,
+    compute: Option<RawCompute>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCompute {
+    path: String,
+    buffer_size: u64,
+    texture_size: [u32; 2],
+    #[serde(default = "default_workgroup_size")]
+    workgroup_size: [u32; 2],
+}
+
+fn default_workgroup_size() -> [u32; 2] {
+    [8, 8]
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawModel {
+    path: String,
+    #[serde(default)]
+    position: [f32; 3],
+    #[serde(default)]
+    rotation: [f32; 3],
+    #[serde(default = "default_scale")]
+    scale: f32,
+    #[serde(default)]
+    cull_mode: RawCullMode,
+    #[serde(default)]
+    topology: RawTopology,
+    #[serde(default)]
+    wireframe: bool,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RawCullMode {
+    None,
+    Front,
+    #[default]
+    Back,
+}
+
+impl RawCullMode {
+    fn face(self) -> Option<Face> {
+        match self {
+            Self::None => None,
+            Self::Front => Some(Face::Front),
+            Self::Back => Some(Face::Back),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RawTopology {
+    PointList,
+    LineList,
+    LineStrip,
+    #[default]
+    TriangleList,
+    TriangleStrip,
+}
+
+impl RawTopology {
+    fn topology(self) -> PrimitiveTopology {
+        match self {
+            Self::PointList => PrimitiveTopology::PointList,
+            Self::LineList => PrimitiveTopology::LineList,
+            Self::LineStrip => PrimitiveTopology::LineStrip,
+            Self::TriangleList => PrimitiveTopology::TriangleList,
+            Self::TriangleStrip => PrimitiveTopology::TriangleStrip,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged, expecting = "a number or an array of 2 to 4 numbers")]
+enum RawParam {
+    Scalar(f32),
+    Vector(Vec<f32>),
+}
+
+impl RawParam {
+    fn values(&self) -> &[f32] {
+        match self {
+            Self::Scalar(value) => std::slice::from_ref(value),
+            Self::Vector(values) => values,
+        }
+    }
+}
+
+// an error about the value at `key`
+fn invalid(key: impl Display, message: impl Display) -> anyhow::Error {
+    anyhow!("{}: {}", key, message)
+}
+
+// NaN fails every check, so it's reported like any other invalid number
+fn ensure(valid: bool, key: impl Display, message: impl Display) -> Result<()> {
+    if valid { Ok(()) } else { Err(invalid(key, message)) }
+}
+
+fn deserialize<T: DeserializeOwned>(text: &str, format: SceneFormat) -> Result<T> {
+    Ok(match format {
+        SceneFormat::Toml => serde_path_to_error::deserialize(toml::Deserializer::parse(text)?)?,
+        SceneFormat::Json => {
+            let mut deserializer = serde_json::Deserializer::from_str(text);
+            let value = serde_path_to_error::deserialize(&mut deserializer)?;
+            deserializer.end()?;
+            value
+        }
+        SceneFormat::Ron => {
+            let mut deserializer = ron::Deserializer::from_str(text)?;
+            // the path is known once deserializing failed, the position only to the deserializer
+            let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|error| {
+                let path = error.path().to_string();
+                let error = deserializer.span_error(error.into_inner());
+                if path == "." { anyhow!("{}", error) } else { invalid(path, error) }
+            })?;
+            deserializer.end().map_err(|error| deserializer.span_error(error))?;
+            value
+        }
+    })
+}
+
+/// A scene described in a TOML, JSON or RON file, with every file it names read.
+///
+/// Paths are relative to the scene file. Every key is optional:
+///
+/// ```toml
+/// shader = "waves"                  # a bundled shader or one of `shaders`
+/// clear_color = [0.1, 0.2, 0.3, 1.0]
+/// particles = "fountain.ron"        # emitter definitions, simulated from the start
+///
+/// [camera]                          # these are the defaults
+/// position = [0.0, 5.0, 10.0]
+/// yaw = -90.0
+/// pitch = -20.0
+/// fov = 45.0
+/// near = 0.1
+/// far = 100.0
+/// speed = 4.0
+/// sensitivity = 0.4
+///
+/// [shaders.waves]                   # shaders cycle in alphabetical order after the bundled ones
+/// path = "waves.wgsl"
+/// channels = ["noise.png"]          # up to 4, `channel0` and `channel0_sampler` in `inputs.wgsl`
+///
+/// [shaders.waves.compute]           # a compute pass feeding the shader
+/// path = "waves_compute.wgsl"
+/// buffer_size = 262144
+/// texture_size = [256, 256]
+/// workgroup_size = [8, 8]
+///
+/// [[models]]                        # OBJ files, with their MTL libraries and diffuse textures
+/// path = "crate.obj"
+/// position = [0.0, 0.0, 0.0]
+/// rotation = [0.0, 45.0, 0.0]       # degrees around x, then y, then z
+/// scale = 1.0
+/// cull_mode = "back"                # or "front" and "none"
+/// topology = "triangle_list"        # or "triangle_strip", "line_list", "line_strip" and "point_list"
+/// wireframe = false                 # edges only, where the adapter supports it
+///
+/// [[lights]]                        # up to 4 point lights for the models
+/// position = [2.0, 5.0, 2.0]
+/// color = [1.0, 1.0, 1.0]
+/// intensity = 1.0
+/// range = 20.0
+///
+/// [params]                          # `params.speed` and `params.tint` in `inputs.wgsl`
+/// speed = 1.5
+/// tint = [1.0, 0.5, 0.2]
+/// ```
+///
+/// Errors name the key of the offending value, such as `shaders.waves.channels[0]`.
+#[derive(Debug, Clone)]
+pub struct SceneFile {
+    pub(super) shader: Option<String>,
+    pub(super) shaders: Vec<(String, ShaderProgram)>,
+    pub(super) camera: CameraSettings,
+    pub(super) particles: Option<String>,
+    pub(super) content: SceneContent,
+}
+
+impl SceneFile {
+    /// Reads a `.toml`, `.json` or `.ron` file and the files it names.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> Result<Self> {
+        let Some(format) = path.extension().and_then(|extension| SceneFormat::from_extension(&extension.to_string_lossy())) else {
+            bail!("`{}` is not a .toml, .json or .ron file", path.display());
+        };
+        let text = std::fs::read_to_string(path).map_err(|error| anyhow!("couldn't read `{}`: {}", path.display(), error))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        Self::parse_with(&text, format, |file| {
+            std::fs::read(base.join(file)).map_err(|error| anyhow!("couldn't read `{}`: {}", file, error))
+        }).map_err(|error| anyhow!("{}: {:#}", path.display(), error))
+    }
+
+    /// Parses a scene that names no files, e.g. one picking a bundled shader, camera and parameters.
+    pub fn parse(text: &str, format: SceneFormat) -> Result<Self> {
+        Self::parse_with(text, format, |file| bail!("can't read `{}`, files need SceneFile::parse_with", file))
+    }
+
+    /// Parses a scene, getting the contents of the files it names from `read`, called with paths as written.
+    pub fn parse_with(text: &str, format: SceneFormat, read: impl Fn(&str) -> Result<Vec<u8>>) -> Result<Self> {
+        let raw: RawScene = deserialize(text, format)?;
+        let read = &read;
+
+        let camera = raw.camera;
+        ensure(camera.fov > 0.0 && camera.fov < 180.0, "camera.fov", format!("must be between 0 and 180 degrees, found {}", camera.fov))?;
+        ensure(camera.near > 0.0, "camera.near", format!("must be positive, found {}", camera.near))?;
+        ensure(camera.far > camera.near, "camera.far", format!("must be beyond camera.near ({}), found {}", camera.near, camera.far))?;
+        ensure(camera.speed >= 0.0, "camera.speed", format!("must not be negative, found {}", camera.speed))?;
+        ensure(camera.sensitivity >= 0.0, "camera.sensitivity", format!("must not be negative, found {}", camera.sensitivity))?;
+
+        for (name, param) in &raw.params {
+            let len = param.values().len();
+            ensure((1..=4).contains(&len), format!("params.{}", name), format!("expected 1 to 4 numbers, found {}", len))?;
+        }
+        let params = Params::new(raw.params.iter().map(|(name, param)| (name.as_str(), param.values())))
+            .map_err(|error| invalid("params", error))?;
+
+        let shaders = raw.shaders.iter().map(|(name, shader)| {
+            let key = format!("shaders.{}", name);
+            let program = load_program(&key, shader, read)?;
+            program.validate_with(&params).map_err(|error| invalid(&key, format!("{:#}", error)))?;
+            Ok((name.clone(), program))
+        }).collect::<Result<Vec<_>>>()?;
+
+        if let Some(shader) = &raw.shader {
+            let names: Vec<_> = ShaderProgram::bundled_names().map(String::from).chain(raw.shaders.keys().cloned()).collect();
+            ensure(names.contains(shader), "shader", format!("unknown shader `{}`, available: {}", shader, names.join(", ")))?;
+        }
+
+        let particles = match &raw.particles {
+            Some(path) => {
+                let bytes = read(path).map_err(|error| invalid("particles", error))?;
+                let definitions = String::from_utf8(bytes).map_err(|error| invalid("particles", error))?;
+                ParticleSystemDescriptor::from_ron(&definitions).map_err(|error| invalid("particles", error))?;
+                Some(definitions)
+            }
+            None => None,
+        };
+
+        let models = raw.models.iter().enumerate().map(|(index, model)| {
+            let key = format!("models[{}]", index);
+            ensure(model.scale > 0.0, format!("{}.scale", key), format!("must be positive, found {}", model.scale))?;
+            // material libraries and textures are relative to the model
+            let directory = Path::new(&model.path).parent().unwrap_or(Path::new(""));
+            let read_relative = |file: &str| read(&directory.join(file).to_string_lossy());
+            let data = ModelData::from_obj(&read(&model.path).map_err(|error| invalid(format!("{}.path", key), error))?, &read_relative)
+                .map_err(|error| invalid(format!("{}.path", key), format!("{:#}", error)))?;
+            let placement = Placement { position: model.position, rotation: model.rotation, scale: model.scale };
+            let description = PipelineDescription {
+                cull_mode: model.cull_mode.face(),
+                topology: model.topology.topology(),
+                wireframe: model.wireframe,
+                ..PipelineDescription::OPAQUE
+            };
+            Ok(ModelDescriptor { data: Arc::new(data), placement, description })
+        }).collect::<Result<Vec<_>>>()?;
+
+        ensure(raw.lights.len() <= MAX_LIGHTS, "lights", format!("at most {} lights are supported, found {}", MAX_LIGHTS, raw.lights.len()))?;
+        for (index, light) in raw.lights.iter().enumerate() {
+            ensure(light.range > 0.0, format!("lights[{}].range", index), format!("must be positive, found {}", light.range))?;
+            ensure(light.intensity >= 0.0, format!("lights[{}].intensity", index), format!("must not be negative, found {}", light.intensity))?;
+        }
+
+        let defaults = SceneContent::default();
+        Ok(Self {
+            shader: raw.shader,
+            shaders,
+            camera,
+            particles,
+            content: SceneContent {
+                clear_color: raw.clear_color.unwrap_or(defaults.clear_color),
+                params,
+                models,
+                lights: raw.lights,
+            },
+        })
+    }
+
+    /// Name of the shader the scene starts with, when it picks one.
+    pub fn shader(&self) -> Option<&str> {
+        self.shader.as_deref()
+    }
+
+    /// Names of the shaders the scene declares, in cycling order.
+    pub fn shader_names(&self) -> impl Iterator<Item = &str> {
+        self.shaders.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+// reads the stages and channels of the shader declared at `key`
+fn load_program(key: &str, shader: &RawShader, read: &dyn Fn(&str) -> Result<Vec<u8>>) -> Result<ShaderProgram> {
+    let code = |key: String, path: &str| -> Result<ShaderCode> {
+        let extension = Path::new(path).extension().map(|extension| extension.to_string_lossy()).unwrap_or_default();
+        ShaderCode::from_bytes(&extension, read(path).map_err(|error| invalid(&key, error))?).map_err(|error| invalid(&key, error))
+    };
+    let mut program = ShaderProgram::new(code(format!("{}.path", key), &shader.path)?);
+
+    if let Some(compute) = &shader.compute {
+        let key = format!("{}.compute", key);
+        let [width, height] = compute.texture_size;
+        ensure(width > 0 && height > 0, format!("{}.texture_size", key), "must not be zero")?;
+        ensure(compute.buffer_size > 0 && compute.buffer_size % 4 == 0, format!("{}.buffer_size", key),
+               format!("must be a positive multiple of 4, found {}", compute.buffer_size))?;
+        ensure(!compute.workgroup_size.contains(&0), format!("{}.workgroup_size", key), "must not be zero")?;
+        program = program.with_compute(ComputeDescriptor {
+            code: code(format!("{}.path", key), &compute.path)?,
+            buffer_size: compute.buffer_size,
+            texture_size: (width, height),
+            workgroup_size: (compute.workgroup_size[0], compute.workgroup_size[1]),
+        });
+    }
+
+    ensure(shader.channels.len() <= MAX_CHANNELS, format!("{}.channels", key),
+           format!("at most {} channels are supported, found {}", MAX_CHANNELS, shader.channels.len()))?;
+    for (index, path) in shader.channels.iter().enumerate() {
+        let key = format!("{}.channels[{}]", key, index);
+        let image = image::load_from_memory(&read(path).map_err(|error| invalid(&key, error))?)
+            .map_err(|error| invalid(&key, format!("`{}`: {}", path, error)))?;
+        program = program.with_channel(image.to_rgba8());
+    }
+    Ok(program)
+}
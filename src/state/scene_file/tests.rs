@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Result};
+use wgpu::{Face, PrimitiveTopology};
+use super::{SceneFile, SceneFormat};
+
+// a triangle in the xy plane
+const TRIANGLE: &[u8] = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+
+fn error(text: &str, format: SceneFormat) -> String {
+    format!("{:#}", SceneFile::parse(text, format).expect_err("the scene should be rejected"))
+}
+
+// reads the files a TOML scene names from `files`, by path as written
+fn parse_with_files(text: &str, files: &[(&str, &[u8])]) -> Result<SceneFile> {
+    SceneFile::parse_with(text, SceneFormat::Toml, |path| {
+        files.iter().find(|(name, _)| *name == path).map(|(_, bytes)| bytes.to_vec()).ok_or_else(|| anyhow!("no file `{}`", path))
+    })
+}
+
+#[test]
+fn formats_follow_extensions() {
+    assert_eq!(SceneFormat::from_extension("toml"), Some(SceneFormat::Toml));
+    assert_eq!(SceneFormat::from_extension("json"), Some(SceneFormat::Json));
+    assert_eq!(SceneFormat::from_extension("ron"), Some(SceneFormat::Ron));
+    assert_eq!(SceneFormat::from_extension("yaml"), None);
+}
+
+#[test]
+fn every_format_reads_the_same_scene() {
+    for (text, format) in [
+        ("shader = \"hash\"\n[camera]\nfov = 60.0\n[params]\nspeed = 2.0\ntint = [1.0, 0.5]", SceneFormat::Toml),
+        (r#"{"shader": "hash", "camera": {"fov": 60.0}, "params": {"speed": 2.0, "tint": [1.0, 0.5]}}"#, SceneFormat::Json),
+        ("(shader: Some(\"hash\"), camera: (fov: 60.0), params: {\"speed\": 2.0, \"tint\": [1.0, 0.5]})", SceneFormat::Ron),
+    ] {
+        let scene = SceneFile::parse(text, format).unwrap_or_else(|error| panic!("{:?}: {:#}", format, error));
+        assert_eq!(scene.shader(), Some("hash"));
+        assert_eq!(scene.camera.fov, 60.0);
+        // the rest of the camera keeps its defaults
+        assert_eq!(scene.camera.far, 100.0);
+        assert_eq!(scene.content.params.get("speed"), Some(&[2.0][..]));
+        assert_eq!(scene.content.params.get("tint"), Some(&[1.0, 0.5][..]));
+    }
+}
+
+#[test]
+fn camera_errors_name_the_key() {
+    assert_eq!(error("[camera]\nfar = 0.05", SceneFormat::Toml), "camera.far: must be beyond camera.near (0.1), found 0.05");
+    assert_eq!(error("[camera]\nfov = 180.0", SceneFormat::Toml), "camera.fov: must be between 0 and 180 degrees, found 180");
+    assert_eq!(error("[camera]\nnear = nan", SceneFormat::Toml), "camera.near: must be positive, found NaN");
+    assert!(error("[camera]\nspeed = -1.0", SceneFormat::Toml).starts_with("camera.speed: "));
+}
+
+#[test]
+fn light_errors_name_the_index() {
+    let second = "[[lights]]\nposition = [0.0, 0.0, 0.0]\n[[lights]]\nposition = [0.0, 0.0, 0.0]\nrange = 0.0";
+    assert_eq!(error(second, SceneFormat::Toml), "lights[1].range: must be positive, found 0");
+    assert_eq!(error("(lights: [(position: (0.0, 0.0, 0.0), intensity: -1.0)])", SceneFormat::Ron),
+               "lights[0].intensity: must not be negative, found -1");
+    let five = "[[lights]]\nposition = [0.0, 0.0, 0.0]\n".repeat(5);
+    assert_eq!(error(&five, SceneFormat::Toml), "lights: at most 4 lights are supported, found 5");
+}
+
+#[test]
+fn shader_and_param_errors_name_the_key() {
+    assert_eq!(error("shader = \"nope\"", SceneFormat::Toml), "shader: unknown shader `nope`, available: default, hash, plasma, life");
+    assert_eq!(error("[params]\nspeed = [1.0, 2.0, 3.0, 4.0, 5.0]", SceneFormat::Toml), "params.speed: expected 1 to 4 numbers, found 5");
+    assert!(error("[params]\nspeed = \"fast\"", SceneFormat::Toml).starts_with("params.speed: "));
+}
+
+#[test]
+fn syntax_errors_carry_the_path() {
+    // TOML and JSON tell where in the file, RON where on the line
+    assert!(error("[camera]\nfvo = 20.0", SceneFormat::Toml).starts_with("camera.fvo: TOML parse error at line 2, column 1"));
+    assert!(error("[camera]\nfar = \"far\"", SceneFormat::Toml).starts_with("camera.far: TOML parse error at line 2, column 7"));
+    assert_eq!(error(r#"{"camera": {"near": "x"}}"#, SceneFormat::Json),
+               "camera.near: invalid type: string \"x\", expected f32 at line 1 column 23");
+    assert_eq!(error("(camera: (far: \"far\"))", SceneFormat::Ron), "camera.far: 1:16: Expected float");
+    assert!(error("(camera: (fvo: 1.0))", SceneFormat::Ron).starts_with("camera.fvo: 1:14: Unexpected field named `fvo`"));
+    // errors before any key have no path to show
+    assert!(!error("(camera: ", SceneFormat::Ron).starts_with('.'));
+    assert!(error("{} {}", SceneFormat::Json).contains("trailing characters"));
+}
+
+#[test]
+fn channel_errors_name_the_channel() {
+    let waves = "[shaders.waves]\npath = \"waves.wgsl\"\nchannels = [\"noise.png\"]";
+    let error = |files: &[(&str, &[u8])]| format!("{:#}", parse_with_files(waves, files).expect_err("the scene should be rejected"));
+    assert_eq!(error(&[]), "shaders.waves.path: no file `waves.wgsl`");
+    assert_eq!(error(&[("waves.wgsl", b"")]), "shaders.waves.channels[0]: no file `noise.png`");
+    assert!(error(&[("waves.wgsl", b""), ("noise.png", b"not a png")]).starts_with("shaders.waves.channels[0]: `noise.png`: "));
+    let five = "[shaders.waves]\npath = \"waves.wgsl\"\nchannels = [\"a\", \"b\", \"c\", \"d\", \"e\"]";
+    assert_eq!(format!("{:#}", parse_with_files(five, &[("waves.wgsl", b"")]).unwrap_err()),
+               "shaders.waves.channels: at most 4 channels are supported, found 5");
+}
+
+#[test]
+fn compute_errors_name_the_key() {
+    let compute = |settings: &str| {
+        let text = format!("[shaders.life]\npath = \"life.wgsl\"\n[shaders.life.compute]\npath = \"step.wgsl\"\n{}", settings);
+        format!("{:#}", parse_with_files(&text, &[("life.wgsl", b""), ("step.wgsl", b"")]).expect_err("the scene should be rejected"))
+    };
+    assert_eq!(compute("buffer_size = 6\ntexture_size = [8, 8]"), "shaders.life.compute.buffer_size: must be a positive multiple of 4, found 6");
+    assert_eq!(compute("buffer_size = 16\ntexture_size = [0, 8]"), "shaders.life.compute.texture_size: must not be zero");
+    assert_eq!(compute("buffer_size = 16\ntexture_size = [8, 8]\nworkgroup_size = [8, 0]"), "shaders.life.compute.workgroup_size: must not be zero");
+}
+
+#[test]
+fn models_are_read_with_how_they_are_drawn() {
+    let scene = parse_with_files("[[models]]\npath = \"models/triangle.obj\"\n\
+                                  [[models]]\npath = \"models/triangle.obj\"\ncull_mode = \"none\"\ntopology = \"line_list\"\nwireframe = true",
+                                 &[("models/triangle.obj", TRIANGLE)]).unwrap();
+    let [first, second] = &scene.content.models[..] else { panic!("expected 2 models") };
+    assert_eq!(first.description.cull_mode, Some(Face::Back));
+    assert_eq!(first.description.topology, PrimitiveTopology::TriangleList);
+    assert!(!first.description.wireframe);
+    assert_eq!(second.description.cull_mode, None);
+    assert_eq!(second.description.topology, PrimitiveTopology::LineList);
+    assert!(second.description.wireframe);
+    assert_eq!(first.placement.scale, 1.0);
+}
+
+#[test]
+fn model_errors_name_the_index() {
+    let files: &[(&str, &[u8])] = &[("triangle.obj", TRIANGLE)];
+    let error = |text: &str| format!("{:#}", parse_with_files(text, files).expect_err("the scene should be rejected"));
+    assert_eq!(error("[[models]]\npath = \"triangle.obj\"\n[[models]]\npath = \"triangle.obj\"\nscale = 0.0"),
+               "models[1].scale: must be positive, found 0");
+    assert_eq!(error("[[models]]\npath = \"missing.obj\""), "models[0].path: no file `missing.obj`");
+    assert!(error("[[models]]\npath = \"triangle.obj\"\ncull_mode = \"sideways\"").starts_with("models[0].cull_mode: "));
+    // material libraries are read next to the model
+    let textured = b"mtllib triangle.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n";
+    let scene = "[[models]]\npath = \"models/triangle.obj\"";
+    let mtl: &[u8] = b"newmtl red\nKd 1 0 0\n";
+    assert_eq!(format!("{:#}", parse_with_files(scene, &[("models/triangle.obj", textured), ("triangle.mtl", mtl)]).unwrap_err()),
+               "models[0].path: material library `triangle.mtl`: no file `models/triangle.mtl`");
+    assert!(parse_with_files(scene, &[("models/triangle.obj", textured), ("models/triangle.mtl", mtl)]).is_ok());
+}
+
+#[test]
+fn particle_counts_must_fit_together() {
+    let emitter = |count: u32| format!("(position: (0.0, 0.0, 0.0), direction: (0.0, 1.0, 0.0), count: {}, lifetime: 1.0, speed: 1.0, \
+                                         spread: 0.0, size: 0.1, color: (1.0, 1.0, 1.0, 1.0))", count);
+    let particles = |counts: [u32; 2]| format!("(emitters: [{}, {}])", emitter(counts[0]), emitter(counts[1]));
+    let scene = "particles = \"particles.ron\"";
+    let fitting = particles([u32::MAX - 1, 1]);
+    assert!(parse_with_files(scene, &[("particles.ron", fitting.as_bytes())]).is_ok());
+    let overflowing = particles([u32::MAX, 1]);
+    assert_eq!(format!("{:#}", parse_with_files(scene, &[("particles.ron", overflowing.as_bytes())]).unwrap_err()),
+               "particles: the emitters have more than 4294967295 particles together");
+}
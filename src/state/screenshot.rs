@@ -0,0 +1,105 @@
+use std::{future::Future, pin::Pin, task::{Context, Poll}};
+use anyhow::Result;
+use image::RgbaImage;
+use super::texture::readback::Readback;
+
+/// A frame on its way back from the GPU, see [`Renderer::screenshot`](crate::Renderer::screenshot).
+///
+/// Resolves to the image once the GPU is done with it, which natively takes polling the device.
+#[derive(Debug)]
+pub struct Screenshot {
+    readback: Readback,
+    // milliseconds since the Unix epoch
+    taken: f64,
+}
+
+impl Screenshot {
+    pub(in crate::state) fn new(readback: Readback) -> Self {
+        Self { readback, taken: now() }
+    }
+
+    /// The image if the GPU is done with it, without waiting.
+    pub fn try_take(&self) -> Option<Result<RgbaImage>> {
+        self.readback.try_take()
+    }
+
+    /// Name to save it under, from when it was taken in UTC, like `screenshot-20240501-133700-042.png`.
+    pub fn file_name(&self) -> String {
+        let millis = self.taken as i64;
+        let (days, millis) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+        let (year, month, day) = civil_date(days);
+        let seconds = millis / 1000;
+        format!("screenshot-{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}.png",
+                year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60, millis % 1000)
+    }
+}
+
+impl Future for Screenshot {
+    type Output = Result<RgbaImage>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.get_mut().readback).poll(cx)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    since_epoch.as_secs_f64() * 1000.0
+}
+
+// the standard library has no clock in browsers
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    js_sys::Date::now()
+}
+
+// year, month and day of the proleptic Gregorian calendar `days` after 1970-01-01
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    // counted from 0000-03-01, so leap days end the 400 year eras and 4 year cycles
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Writes `image` as a PNG on another thread, encoding large ones takes a while.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn save(image: RgbaImage, path: std::path::PathBuf) {
+    std::thread::spawn(move || {
+        let result = match path.parent() {
+            Some(directory) => std::fs::create_dir_all(directory).map_err(image::ImageError::IoError),
+            None => Ok(()),
+        };
+        match result.and_then(|()| image.save(&path)) {
+            Ok(()) => log::info!("Saved screenshot `{}`", path.display()),
+            Err(error) => log::error!("Couldn't save screenshot `{}`: {}", path.display(), error),
+        }
+    });
+}
+
+/// Hands `image` to the browser as a PNG download called `file_name`.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn download(image: &RgbaImage, file_name: &str) -> Result<()> {
+    use anyhow::{anyhow, Context as _};
+    use wasm_bindgen::JsCast;
+
+    let js = |error: wasm_bindgen::JsValue| anyhow!("{:?}", error);
+    let mut png = Vec::new();
+    image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(png.as_slice()));
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, web_sys::BlobPropertyBag::new().type_("image/png")).map_err(js)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js)?;
+    let document = web_sys::window().and_then(|window| window.document()).context("no document to download from")?;
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a").map_err(js)?.dyn_into().map_err(|_| anyhow!("`a` isn't an anchor"))?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(js)
+}
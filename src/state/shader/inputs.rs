@@ -0,0 +1,33 @@
+use std::{fmt::Write, iter};
+use wgpu::{BindGroupLayoutEntry, ShaderStages};
+use crate::state::uniform::{bundle::{texture_entries, uniform_entry}, params::Params};
+
+/// Channel textures a shader can read, like Shadertoy's `iChannel0` to `iChannel3`.
+pub(in crate::state) const MAX_CHANNELS: usize = 4;
+
+/// Source of `inputs.wgsl`, declaring the parameters as `params` and every channel as `channelN` with
+/// its sampler `channelN_sampler`, in that order from `@binding(0)` of `group`.
+pub(in crate::state) fn inputs_wgsl(group: u32, params: &Params, channels: usize) -> String {
+    let mut wgsl = params.wgsl();
+    let mut binding = 0;
+    if !params.is_empty() {
+        let _ = writeln!(wgsl, "@group({}) @binding({})\nvar<uniform> params: Params;", group, binding);
+        binding += 1;
+    }
+    for channel in 0..channels {
+        let _ = writeln!(wgsl, "@group({}) @binding({})\nvar channel{}: texture_2d<f32>;", group, binding, channel);
+        let _ = writeln!(wgsl, "@group({}) @binding({})\nvar channel{}_sampler: sampler;", group, binding + 1, channel);
+        binding += 2;
+    }
+    wgsl
+}
+
+/// Entries of the group `inputs.wgsl` declares, empty when there's nothing to bind.
+pub(in crate::state) fn inputs_layout_entries(params: &Params, channels: usize) -> Vec<BindGroupLayoutEntry> {
+    let params = (!params.is_empty()).then(|| uniform_entry(ShaderStages::FRAGMENT));
+    params.into_iter()
+        .chain(iter::repeat_n(texture_entries(ShaderStages::FRAGMENT), channels).flatten())
+        .enumerate()
+        .map(|(binding, entry)| BindGroupLayoutEntry { binding: binding as u32, ..entry })
+        .collect()
+}
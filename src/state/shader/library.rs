@@ -0,0 +1,174 @@
+use std::{borrow::Cow, sync::Arc};
+use anyhow::{bail, Context, Result};
+use bytemuck::Zeroable;
+use image::RgbaImage;
+use crate::state::{
+    compute::{self, ComputeDescriptor},
+    geometry::quad::QuadVertex,
+    uniform::{Uniform, camera::CameraUniform, params::Params, system::SystemUniform}
+};
+use super::{parse_shader, ShaderCode, inputs::{inputs_layout_entries, inputs_wgsl, MAX_CHANNELS}, preprocessor::Preprocessor};
+
+/// A render shader, the compute pass feeding it, if any, and the textures it samples.
+///
+/// A WGSL shader including `inputs.wgsl` sees channel `N` as `channelN` and `channelN_sampler`,
+/// next to the `params` of the scene, in the group after the uniforms and compute results.
+#[derive(Debug, Clone)]
+pub struct ShaderProgram {
+    /// Shader with the `vs_main` and `fs_main` entry points.
+    pub render: ShaderCode,
+    /// Compute pass run before every frame.
+    pub compute: Option<ComputeDescriptor>,
+    /// Up to four images, bound in order.
+    pub channels: Vec<Arc<RgbaImage>>,
+}
+
+impl ShaderProgram {
+    /// The bundled program called `name`.
+    pub fn bundled(name: &str) -> Option<Self> {
+        BUNDLED.iter().find(|(bundled, _)| *bundled == name).map(|(_, program)| program.clone())
+    }
+
+    /// Names of every bundled program, in cycling order.
+    pub fn bundled_names() -> impl Iterator<Item = &'static str> {
+        BUNDLED.iter().map(|(name, _)| *name)
+    }
+
+    /// A program with nothing but `render`.
+    pub const fn new(render: ShaderCode) -> Self {
+        Self { render, compute: None, channels: Vec::new() }
+    }
+
+    /// Runs `compute` before every frame.
+    pub fn with_compute(mut self, compute: ComputeDescriptor) -> Self {
+        self.compute = Some(compute);
+        self
+    }
+
+    /// Binds `image` as the next channel.
+    pub fn with_channel(mut self, image: RgbaImage) -> Self {
+        self.channels.push(Arc::new(image));
+        self
+    }
+
+    /// Parses every stage and checks it against the bind groups and vertex buffer the renderer
+    /// provides, without a GPU. Drivers may still reject what naga accepts.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_with(&Params::default())
+    }
+
+    /// Like [`validate`](Self::validate), for a scene declaring `params`.
+    pub(in crate::state) fn validate_with(&self, params: &Params) -> Result<()> {
+        if self.channels.len() > MAX_CHANNELS {
+            bail!("shader has {} channels, at most {} are supported", self.channels.len(), MAX_CHANNELS);
+        }
+        let preprocessor = Preprocessor::new();
+        let mut groups = vec![
+            vec![SystemUniform::zeroed().get_layout_entry()],
+            vec![CameraUniform::zeroed().get_layout_entry()],
+        ];
+
+        if let Some(desc) = &self.compute {
+            let shader = parse_shader(&preprocessor, &desc.code).context("compute shader")?;
+            if shader.compute_entry().is_none() {
+                bail!("compute shader has no compute entry point");
+            }
+            let storage = compute::storage_layout_entries();
+            shader.reflection().validate(&[&groups[0], &groups[1], &storage], &[]).context("compute shader")?;
+            groups.push(compute::output_layout_entries());
+        }
+
+        let inputs = inputs_layout_entries(params, self.channels.len());
+        let preprocessor = preprocessor.with_source("inputs.wgsl", inputs_wgsl(groups.len() as u32, params, self.channels.len()));
+        if !inputs.is_empty() {
+            groups.push(inputs);
+        }
+        let shader = parse_shader(&preprocessor, &self.render)?;
+        if !shader.has_fragment() {
+            bail!("shader has no fragment entry point");
+        }
+        let groups: Vec<_> = groups.iter().map(Vec::as_slice).collect();
+        shader.reflection().validate(&groups, &[QuadVertex::desc()])
+    }
+}
+
+// shaders compiled into the binary, selectable by name
+const BUNDLED: &[(&str, ShaderProgram)] = &[
+    ("default", ShaderProgram::new(ShaderCode::Wgsl(Cow::Borrowed(include_str!("../../default.wgsl"))))),
+    ("hash", ShaderProgram::new(ShaderCode::Wgsl(Cow::Borrowed(include_str!("../../hash.wgsl"))))),
+    ("plasma", ShaderProgram::new(ShaderCode::Glsl(Cow::Borrowed(include_str!("../../plasma.glsl"))))),
+    ("life", ShaderProgram {
+        render: ShaderCode::Wgsl(Cow::Borrowed(include_str!("../../life.wgsl"))),
+        compute: Some(ComputeDescriptor {
+            code: ShaderCode::Wgsl(Cow::Borrowed(include_str!("../../life_compute.wgsl"))),
+            // two generations of 256x256 u32 cells
+            buffer_size: 2 * 256 * 256 * 4,
+            texture_size: (256, 256),
+            workgroup_size: (8, 8),
+        }),
+        channels: Vec::new(),
+    }),
+];
+
+/// Named collection of shader programs with a cursor on the active one.
+#[derive(Debug, Clone)]
+pub(in crate::state) struct ShaderLibrary {
+    shaders: Vec<(String, ShaderProgram)>,
+    current: usize,
+}
+
+impl ShaderLibrary {
+    pub(in crate::state) fn bundled() -> Self {
+        Self {
+            shaders: BUNDLED.iter().map(|(name, program)| (name.to_string(), program.clone())).collect(),
+            current: 0,
+        }
+    }
+
+    /// Reads a `.wgsl`, `.glsl`/`.frag` or `.spv` file and registers it under its file stem.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(in crate::state) fn load(&mut self, path: &std::path::Path) -> Result<String> {
+        let name = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => bail!("`{}` is not a file", path.display()),
+        };
+        self.insert(&name, ShaderProgram::new(ShaderCode::from_file(path)?));
+        Ok(name)
+    }
+
+    /// Registers `program` under `name`, replacing any shader called the same.
+    pub(in crate::state) fn insert(&mut self, name: &str, program: ShaderProgram) {
+        match self.shaders.iter_mut().find(|(shader, _)| shader == name) {
+            Some(entry) => entry.1 = program,
+            None => self.shaders.push((name.to_string(), program)),
+        }
+    }
+
+    pub(in crate::state) fn names(&self) -> impl Iterator<Item = &str> {
+        self.shaders.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub(in crate::state) fn get(&self, name: &str) -> Option<&ShaderProgram> {
+        self.shaders.iter().find(|(shader, _)| shader == name).map(|(_, program)| program)
+    }
+
+    pub(in crate::state) fn current(&self) -> &str {
+        &self.shaders[self.current].0
+    }
+
+    pub(in crate::state) fn set_current(&mut self, name: &str) {
+        if let Some(index) = self.shaders.iter().position(|(shader, _)| shader == name) {
+            self.current = index;
+        }
+    }
+
+    pub(in crate::state) fn next(&self) -> &str {
+        self.after(self.current())
+    }
+
+    /// The shader cycling comes to after `name`, the first one when there's no `name`.
+    pub(in crate::state) fn after(&self, name: &str) -> &str {
+        let index = self.shaders.iter().position(|(shader, _)| shader == name).map_or(0, |index| index + 1);
+        &self.shaders[index % self.shaders.len()].0
+    }
+}
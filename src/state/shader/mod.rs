@@ -0,0 +1,218 @@
+pub(crate) mod library;
+pub(super) mod inputs;
+pub(super) mod preprocessor;
+pub(super) mod reflect;
+
+use std::{borrow::Cow, collections::hash_map::DefaultHasher, hash::{Hash, Hasher}};
+use anyhow::{anyhow, bail, Result};
+use naga::{front::{glsl, spv, wgsl}, Binding, Module, Sampling, ShaderStage};
+use wgpu::{
+    ColorTargetState, Device, FragmentState, ShaderModule, ShaderModuleDescriptor,
+    ShaderSource, VertexBufferLayout, VertexState
+};
+use self::{preprocessor::Preprocessor, reflect::Reflection};
+
+// wraps a Shadertoy `mainImage` into a fragment entry point bound to our uniforms
+const SHADERTOY_PRELUDE: &str = include_str!("../../include/shadertoy.glsl");
+
+/// Shader source in one of the languages naga can translate.
+#[derive(Debug, Clone, Hash)]
+pub enum ShaderCode {
+    /// WGSL with `vs_main`/`fs_main` entry points, with `#include`s of the bundled library resolved.
+    Wgsl(Cow<'static, str>),
+    /// Shadertoy GLSL defining `mainImage(out vec4, in vec2)`.
+    Glsl(Cow<'static, str>),
+    /// Precompiled SPIR-V with a fragment entry point and an optional vertex entry point.
+    SpirV(Cow<'static, [u8]>),
+}
+
+impl ShaderCode {
+    #[cfg(not(target_arch = "wasm32"))]
+    const EXTENSIONS: &'static [&'static str] = &["wgsl", "glsl", "frag", "spv"];
+
+    /// Reads a `.wgsl`, `.glsl`/`.frag` or `.spv` file, picking the language by extension.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(path: &std::path::Path) -> Result<Self> {
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        if !Self::EXTENSIONS.contains(&extension) {
+            bail!("`{}` is not a .wgsl, .glsl, .frag or .spv file", path.display());
+        }
+        Self::from_bytes(extension, std::fs::read(path)?)
+    }
+
+    /// Takes the contents of a file with the given extension, as [`from_file`](Self::from_file) would read them.
+    pub fn from_bytes(extension: &str, bytes: Vec<u8>) -> Result<Self> {
+        Ok(match extension {
+            "wgsl" => Self::Wgsl(Cow::Owned(String::from_utf8(bytes)?)),
+            "glsl" | "frag" => Self::Glsl(Cow::Owned(String::from_utf8(bytes)?)),
+            "spv" => Self::SpirV(Cow::Owned(bytes)),
+            _ => bail!("`.{}` is not a .wgsl, .glsl, .frag or .spv extension", extension),
+        })
+    }
+
+    /// Hash of the source, identifying the pipelines compiled from it.
+    pub(in crate::state) fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Hash of the source with the includes of `preprocessor` resolved, for shaders including generated declarations.
+    pub(in crate::state) fn processed_fingerprint(&self, preprocessor: &Preprocessor) -> Result<u64> {
+        let mut hasher = DefaultHasher::new();
+        match self {
+            Self::Wgsl(source) => preprocessor.process(source)?.hash(&mut hasher),
+            code => code.hash(&mut hasher),
+        }
+        Ok(hasher.finish())
+    }
+}
+
+/// Compiled shader stages. Sources with a fragment but no vertex stage get the quad vertex shader.
+#[derive(Debug)]
+pub(super) struct Shader {
+    module: ShaderModule,
+    vertex_module: Option<ShaderModule>,
+    vertex_entry: String,
+    fragment_entry: Option<String>,
+    compute_entry: Option<String>,
+    reflection: Reflection,
+}
+
+impl Shader {
+    pub(super) fn module(&self) -> &ShaderModule {
+        &self.module
+    }
+
+    pub(super) fn has_fragment(&self) -> bool {
+        self.fragment_entry.is_some()
+    }
+
+    pub(super) fn compute_entry(&self) -> Option<&str> {
+        self.compute_entry.as_deref()
+    }
+
+    pub(super) fn reflection(&self) -> &Reflection {
+        &self.reflection
+    }
+
+    pub(super) fn vertex_state<'a>(&'a self, buffers: &'a [VertexBufferLayout<'a>]) -> VertexState<'a> {
+        VertexState {
+            module: self.vertex_module.as_ref().unwrap_or(&self.module),
+            entry_point: &self.vertex_entry,
+            buffers,
+        }
+    }
+
+    /// Panics if the shader has no fragment stage, check [`Shader::has_fragment`] first.
+    pub(super) fn fragment_state<'a>(&'a self, targets: &'a [Option<ColorTargetState>]) -> FragmentState<'a> {
+        FragmentState {
+            module: &self.module,
+            entry_point: self.fragment_entry.as_deref().expect("shader has no fragment stage"),
+            targets,
+        }
+    }
+}
+
+fn parse_wgsl(source: &str) -> Result<Module> {
+    wgsl::parse_str(source).map_err(|error| anyhow!("{}", error.emit_to_string(source)))
+}
+
+fn parse_glsl(source: &str) -> Result<Module> {
+    let source = format!("{}{}", SHADERTOY_PRELUDE, source);
+    let prelude_lines = SHADERTOY_PRELUDE.lines().count() as u32;
+    let mut module = glsl::Parser::default().parse(&glsl::Options::from(ShaderStage::Fragment), &source).map_err(|errors| {
+        let messages: Vec<_> = errors.iter().map(|error| {
+            let line = error.meta.location(&source).line_number;
+            match line.checked_sub(prelude_lines) {
+                Some(line) if line > 0 => format!("line {}: {}", line, error),
+                _ => format!("prelude: {}", error),
+            }
+        }).collect();
+        anyhow!("{}", messages.join("\n"))
+    })?;
+    // the GLSL frontend leaves sampling unset where WGSL defaults to center, wgpu
+    // wouldn't match these inputs to the quad's outputs
+    for argument in module.entry_points.iter_mut().flat_map(|entry| entry.function.arguments.iter_mut()) {
+        if let Some(Binding::Location { interpolation: Some(_), sampling: sampling @ None, .. }) = &mut argument.binding {
+            *sampling = Some(Sampling::Center);
+        }
+    }
+    Ok(module)
+}
+
+fn parse_spirv(bytes: &[u8]) -> Result<Module> {
+    Ok(spv::parse_u8_slice(bytes, &spv::Options::default())?)
+}
+
+fn entry_point(module: &Module, stage: ShaderStage) -> Option<String> {
+    module.entry_points.iter().find(|entry| entry.stage == stage).map(|entry| entry.name.clone())
+}
+
+fn create_module(device: &Device, module: Module) -> ShaderModule {
+    device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("Shader"),
+        source: ShaderSource::Naga(Cow::Owned(module))
+    })
+}
+
+/// Shader stages parsed and validated by naga, not yet handed to a device.
+#[derive(Debug)]
+pub(super) struct ParsedShader {
+    module: Module,
+    quad: Option<Module>,
+    vertex_entry: String,
+    fragment_entry: Option<String>,
+    compute_entry: Option<String>,
+    reflection: Reflection,
+}
+
+impl ParsedShader {
+    pub(super) fn has_fragment(&self) -> bool {
+        self.fragment_entry.is_some()
+    }
+
+    pub(super) fn compute_entry(&self) -> Option<&str> {
+        self.compute_entry.as_deref()
+    }
+
+    pub(super) fn reflection(&self) -> &Reflection {
+        &self.reflection
+    }
+}
+
+pub(super) fn parse_shader(preprocessor: &Preprocessor, code: &ShaderCode) -> Result<ParsedShader> {
+    // everything goes through naga, so GLSL and SPIR-V reach the WebGL backend as well
+    let module = match code {
+        ShaderCode::Wgsl(source) => parse_wgsl(&preprocessor.process(source)?)?,
+        ShaderCode::Glsl(source) => parse_glsl(source)?,
+        ShaderCode::SpirV(bytes) => parse_spirv(bytes)?,
+    };
+
+    let fragment_entry = entry_point(&module, ShaderStage::Fragment);
+    let compute_entry = entry_point(&module, ShaderStage::Compute);
+    if fragment_entry.is_none() && compute_entry.is_none() {
+        bail!("shader has neither a fragment nor a compute entry point");
+    }
+    let (quad, vertex_entry) = match entry_point(&module, ShaderStage::Vertex) {
+        Some(entry) => (None, entry),
+        None if fragment_entry.is_none() => (None, String::new()),
+        None => (Some(parse_wgsl(&preprocessor.process("#include \"quad.wgsl\"")?)?), "vs_main".to_string()),
+    };
+    let reflection = Reflection::new(&[Some(&module), quad.as_ref()].into_iter().flatten().collect::<Vec<_>>())?;
+    log::debug!("Shader interface:\n{}", reflection);
+
+    Ok(ParsedShader { module, quad, vertex_entry, fragment_entry, compute_entry, reflection })
+}
+
+pub(super) fn compile_shader(device: &Device, preprocessor: &Preprocessor, code: &ShaderCode) -> Result<Shader> {
+    let ParsedShader { module, quad, vertex_entry, fragment_entry, compute_entry, reflection } = parse_shader(preprocessor, code)?;
+    Ok(Shader {
+        module: create_module(device, module),
+        vertex_module: quad.map(|quad| create_module(device, quad)),
+        vertex_entry,
+        fragment_entry,
+        compute_entry,
+        reflection,
+    })
+}
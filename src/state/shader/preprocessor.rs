@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+use anyhow::{bail, Result};
+use crate::state::{
+    particle::{Emitter, Particle, Simulation},
+    uniform::{camera::CameraUniform, compare::CompareUniform, light::Light, system::SystemUniform}
+};
+
+// built-in snippets that every shader can `#include`, structs shared with Rust are generated from their types
+const LIBRARY: &[(&str, &str)] = &[
+    ("system_uniform.wgsl", SystemUniform::WGSL),
+    ("camera_uniform.wgsl", CameraUniform::WGSL),
+    ("light.wgsl", Light::WGSL),
+    ("compare_uniform.wgsl", CompareUniform::WGSL),
+    ("particle.wgsl", Particle::WGSL),
+    ("particle_emitter.wgsl", Emitter::WGSL),
+    ("particle_simulation.wgsl", Simulation::WGSL),
+    ("uniforms.wgsl", include_str!("../../include/uniforms.wgsl")),
+    ("quad.wgsl", include_str!("../../include/quad.wgsl")),
+    ("hash.wgsl", include_str!("../../include/hash.wgsl")),
+    ("noise.wgsl", include_str!("../../include/noise.wgsl")),
+    ("compute_output.wgsl", include_str!("../../include/compute_output.wgsl")),
+    ("lights.wgsl", include_str!("../../include/lights.wgsl")),
+];
+
+// state of one `#ifdef`/`#ifndef` block
+struct Conditional {
+    // whether the enclosing block emits lines
+    parent_active: bool,
+    // whether the current branch emits lines
+    active: bool,
+    seen_else: bool,
+}
+
+/// Resolves `#include`, `#define`, `#undef`, `#ifdef`, `#ifndef`, `#else` and `#endif` directives
+/// in WGSL source. Every file is included at most once, so snippets may include each other freely.
+#[derive(Debug, Clone)]
+pub(in crate::state) struct Preprocessor {
+    defines: HashMap<String, String>,
+    sources: HashMap<String, String>,
+}
+
+impl Preprocessor {
+    pub(in crate::state) fn new() -> Self {
+        let mut defines = HashMap::new();
+        if cfg!(target_arch = "wasm32") {
+            defines.insert("TARGET_WASM".to_string(), String::new());
+        } else {
+            defines.insert("TARGET_NATIVE".to_string(), String::new());
+        }
+
+        Self {
+            defines,
+            sources: LIBRARY.iter().map(|(name, source)| (name.to_string(), source.to_string())).collect(),
+        }
+    }
+
+    /// A copy that can also `#include` `source` as `name`, e.g. declarations generated for one shader.
+    pub(in crate::state) fn with_source(&self, name: &str, source: String) -> Self {
+        let mut preprocessor = self.clone();
+        preprocessor.sources.insert(name.to_string(), source);
+        preprocessor
+    }
+
+    pub(in crate::state) fn process(&self, source: &str) -> Result<String> {
+        let mut defines = self.defines.clone();
+        let mut included = HashSet::new();
+        let mut output = String::with_capacity(source.len());
+        self.process_file("<shader>", source, &mut defines, &mut included, &mut output)?;
+        Ok(output)
+    }
+
+    fn process_file(&self, file: &str, source: &str, defines: &mut HashMap<String, String>,
+                    included: &mut HashSet<String>, output: &mut String) -> Result<()> {
+        let mut conditionals: Vec<Conditional> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let active = conditionals.last().is_none_or(|c| c.active);
+            let trimmed = line.trim_start();
+
+            let Some(directive) = trimmed.strip_prefix('#') else {
+                if active {
+                    output.push_str(&substitute(line, defines));
+                    output.push('\n');
+                }
+                continue;
+            };
+
+            let mut parts = directive.splitn(2, char::is_whitespace);
+            let keyword = parts.next().unwrap_or_default();
+            let argument = parts.next().unwrap_or_default().trim();
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let name = identifier(file, line_number, keyword, argument)?;
+                    let defined = defines.contains_key(name);
+                    conditionals.push(Conditional {
+                        parent_active: active,
+                        active: active && (defined == (keyword == "ifdef")),
+                        seen_else: false,
+                    });
+                }
+                "else" => match conditionals.last_mut() {
+                    Some(conditional) if !conditional.seen_else => {
+                        conditional.seen_else = true;
+                        conditional.active = conditional.parent_active && !conditional.active;
+                    }
+                    Some(_) => bail!("{}:{}: duplicate #else", file, line_number),
+                    None => bail!("{}:{}: #else without #ifdef", file, line_number),
+                },
+                "endif" => {
+                    if conditionals.pop().is_none() {
+                        bail!("{}:{}: #endif without #ifdef", file, line_number);
+                    }
+                }
+                _ if !active => {}
+                "define" => {
+                    let mut parts = argument.splitn(2, char::is_whitespace);
+                    let name = identifier(file, line_number, keyword, parts.next().unwrap_or_default())?;
+                    let value = parts.next().unwrap_or_default().trim();
+                    defines.insert(name.to_string(), substitute(value, defines));
+                }
+                "undef" => {
+                    let name = identifier(file, line_number, keyword, argument)?;
+                    defines.remove(name);
+                }
+                "include" => {
+                    let name = match argument.strip_prefix('"').and_then(|name| name.strip_suffix('"')) {
+                        Some(name) => name,
+                        None => bail!("{}:{}: expected #include \"file\", found `{}`", file, line_number, argument),
+                    };
+                    let source = match self.sources.get(name) {
+                        Some(source) => source,
+                        None => bail!("{}:{}: unknown include \"{}\"", file, line_number, name),
+                    };
+                    if included.insert(name.to_string()) {
+                        self.process_file(name, source, defines, included, output)?;
+                    }
+                }
+                _ => bail!("{}:{}: unknown directive #{}", file, line_number, keyword),
+            }
+        }
+
+        if !conditionals.is_empty() {
+            bail!("{}: unterminated #ifdef", file);
+        }
+        Ok(())
+    }
+}
+
+fn identifier<'a>(file: &str, line_number: usize, keyword: &str, argument: &'a str) -> Result<&'a str> {
+    let mut chars = argument.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("{}:{}: #{} expects an identifier, found `{}`", file, line_number, keyword, argument);
+    }
+    Ok(argument)
+}
+
+// replaces every defined identifier outside of `//` comments with its value
+fn substitute(line: &str, defines: &HashMap<String, String>) -> String {
+    let (code, comment) = match line.find("//") {
+        Some(position) => line.split_at(position),
+        None => (line, ""),
+    };
+
+    let mut result = String::with_capacity(line.len());
+    let mut rest = code;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        let (before, word_start) = rest.split_at(start);
+        let end = word_start.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(word_start.len());
+        let (word, after) = word_start.split_at(end);
+        result.push_str(before);
+        // identifiers glued to a preceding digit are literal suffixes such as `1u`
+        let is_suffix = before.ends_with(|c: char| c.is_ascii_digit());
+        match defines.get(word) {
+            Some(value) if !is_suffix => result.push_str(value),
+            _ => result.push_str(word),
+        }
+        rest = after;
+    }
+    result.push_str(rest);
+    result.push_str(comment);
+    result
+}
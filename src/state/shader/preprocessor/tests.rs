@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+use super::Preprocessor;
+use crate::state::shader::ShaderCode;
+
+// the lines `source` preprocesses to, without blank ones
+fn lines(preprocessor: &Preprocessor, source: &str) -> Vec<String> {
+    let output = preprocessor.process(source).unwrap_or_else(|error| panic!("{:#}", error));
+    output.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect()
+}
+
+fn error(preprocessor: &Preprocessor, source: &str) -> String {
+    match preprocessor.process(source) {
+        Ok(output) => panic!("expected an error, got:\n{}", output),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn plain_lines_pass_through() {
+    let preprocessor = Preprocessor::new();
+    assert_eq!(preprocessor.process("let a = 1;\n  let b = 2;").unwrap(), "let a = 1;\n  let b = 2;\n");
+}
+
+#[test]
+fn ifdef_and_ifndef_pick_branches() {
+    let preprocessor = Preprocessor::new();
+    let source = "
+        #define A
+        #ifdef A
+        a
+        #else
+        not a
+        #endif
+        #ifndef B
+        not b
+        #else
+        b
+        #endif
+    ";
+    assert_eq!(lines(&preprocessor, source), ["a", "not b"]);
+}
+
+#[test]
+fn nested_conditionals_only_emit_when_every_level_is_active() {
+    let preprocessor = Preprocessor::new();
+    let source = "
+        #define OUTER
+        #ifdef OUTER
+        outer
+        #ifdef INNER
+        inner
+        #else
+        outer without inner
+        #endif
+        #else
+        #ifdef INNER
+        never
+        #else
+        never either
+        #endif
+        #endif
+        #ifdef MISSING
+        #ifndef INNER
+        hidden by the enclosing block
+        #endif
+        #endif
+        after
+    ";
+    assert_eq!(lines(&preprocessor, source), ["outer", "outer without inner", "after"]);
+}
+
+#[test]
+fn directives_in_inactive_blocks_are_ignored() {
+    let preprocessor = Preprocessor::new();
+    let source = "
+        #define KEPT 1
+        #ifdef MISSING
+        #define KEPT 2
+        #undef KEPT
+        #include \"nowhere.wgsl\"
+        #endif
+        KEPT
+    ";
+    assert_eq!(lines(&preprocessor, source), ["1"]);
+}
+
+#[test]
+fn defines_substitute_whole_identifiers_outside_comments() {
+    let preprocessor = Preprocessor::new();
+    let source = "
+        #define SIZE 8
+        #define DOUBLE SIZE * 2
+        let a = SIZE; let b = SIZES; let c = DOUBLE; // SIZE
+        let d = 1SIZE;
+    ";
+    assert_eq!(lines(&preprocessor, source), ["let a = 8; let b = SIZES; let c = 8 * 2; // SIZE", "let d = 1SIZE;"]);
+}
+
+#[test]
+fn undef_removes_a_define() {
+    let preprocessor = Preprocessor::new();
+    let source = "
+        #define A 1
+        A
+        #undef A
+        A
+        #ifdef A
+        still defined
+        #endif
+    ";
+    assert_eq!(lines(&preprocessor, source), ["1", "A"]);
+}
+
+#[test]
+fn defines_dont_leak_between_runs() {
+    let preprocessor = Preprocessor::new();
+    lines(&preprocessor, "#define LEAK");
+    assert_eq!(lines(&preprocessor, "#ifdef LEAK\nleaked\n#endif"), Vec::<String>::new());
+}
+
+#[test]
+fn target_is_defined() {
+    let preprocessor = Preprocessor::new();
+    assert_eq!(lines(&preprocessor, "#ifdef TARGET_NATIVE\nnative\n#endif\n#ifdef TARGET_WASM\nwasm\n#endif"), ["native"]);
+}
+
+#[test]
+fn files_are_included_once() {
+    let preprocessor = Preprocessor::new()
+        .with_source("a.wgsl", "#include \"b.wgsl\"\nfrom a".to_string())
+        .with_source("b.wgsl", "#include \"a.wgsl\"\nfrom b".to_string());
+    let source = "
+        #include \"a.wgsl\"
+        #include \"b.wgsl\"
+        #include \"a.wgsl\"
+        main
+    ";
+    assert_eq!(lines(&preprocessor, source), ["from b", "from a", "main"]);
+}
+
+#[test]
+fn included_files_share_defines() {
+    let preprocessor = Preprocessor::new().with_source("config.wgsl", "#define LIGHTS 4".to_string());
+    assert_eq!(lines(&preprocessor, "#include \"config.wgsl\"\nLIGHTS"), ["4"]);
+}
+
+#[test]
+fn with_source_leaves_the_original_alone() {
+    let preprocessor = Preprocessor::new();
+    let _extended = preprocessor.with_source("extra.wgsl", "extra".to_string());
+    assert!(error(&preprocessor, "#include \"extra.wgsl\"").contains("unknown include \"extra.wgsl\""));
+}
+
+#[test]
+fn library_snippets_preprocess() {
+    let preprocessor = Preprocessor::new();
+    for (name, _) in super::LIBRARY {
+        preprocessor.process(&format!("#include \"{}\"", name)).unwrap_or_else(|error| panic!("{}: {:#}", name, error));
+    }
+}
+
+#[test]
+fn malformed_directives_are_reported_with_their_line() {
+    let preprocessor = Preprocessor::new();
+    for (source, message) in [
+        ("a\n#else", "<shader>:2: #else without #ifdef"),
+        ("#endif", "<shader>:1: #endif without #ifdef"),
+        ("#ifdef A\n#else\n#else\n#endif", "<shader>:3: duplicate #else"),
+        ("#ifdef A\n", "<shader>: unterminated #ifdef"),
+        ("#ifdef\n#endif", "<shader>:1: #ifdef expects an identifier, found ``"),
+        ("#define 1A", "<shader>:1: #define expects an identifier, found `1A`"),
+        ("#undef A-B", "<shader>:1: #undef expects an identifier, found `A-B`"),
+        ("#include <a.wgsl>", "<shader>:1: expected #include \"file\", found `<a.wgsl>`"),
+        ("\n\n#include \"missing.wgsl\"", "<shader>:3: unknown include \"missing.wgsl\""),
+        ("#pragma once", "<shader>:1: unknown directive #pragma"),
+    ] {
+        assert_eq!(error(&preprocessor, source), message, "for {:?}", source);
+    }
+}
+
+#[test]
+fn errors_in_includes_name_the_file() {
+    let preprocessor = Preprocessor::new().with_source("broken.wgsl", "fine\n#ifdef A".to_string());
+    assert_eq!(error(&preprocessor, "#include \"broken.wgsl\""), "broken.wgsl: unterminated #ifdef");
+    let preprocessor = Preprocessor::new().with_source("broken.wgsl", "fine\n#endif".to_string());
+    assert_eq!(error(&preprocessor, "#include \"broken.wgsl\""), "broken.wgsl:2: #endif without #ifdef");
+}
+
+#[test]
+fn processed_fingerprints_cover_included_sources() {
+    let code = ShaderCode::Wgsl(Cow::Borrowed("#include \"inputs.wgsl\"\nfn main() {}"));
+    let fingerprint = |inputs: &str| code.processed_fingerprint(&Preprocessor::new().with_source("inputs.wgsl", inputs.to_string())).unwrap();
+    assert_eq!(fingerprint("struct Params { speed: f32 }"), fingerprint("struct Params { speed: f32 }"));
+    assert_ne!(fingerprint("struct Params { speed: f32 }"), fingerprint("struct Params { tint: vec2<f32> }"));
+    // other languages can't include anything
+    let glsl = ShaderCode::Glsl(Cow::Borrowed("void mainImage(out vec4 color, in vec2 coord) {}"));
+    assert_eq!(glsl.processed_fingerprint(&Preprocessor::new()).unwrap(), glsl.fingerprint());
+}
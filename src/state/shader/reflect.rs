@@ -0,0 +1,331 @@
+#[cfg(test)]
+mod tests;
+
+use std::{collections::BTreeMap, fmt};
+use anyhow::{bail, Result};
+use naga::{
+    AddressSpace, Binding, ImageClass, ImageDimension, Module, ScalarKind, ShaderStage, StorageAccess, TypeInner,
+    proc::Layouter,
+    valid::{Capabilities, ValidationFlags, Validator}
+};
+use wgpu::{
+    BindGroupLayoutEntry, BindingType, BufferBindingType, SamplerBindingType, ShaderStages,
+    TextureSampleType, TextureViewDimension, VertexBufferLayout, VertexFormat
+};
+
+/// What a shader expects at one `@group`/`@binding`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(in crate::state) enum Resource {
+    Uniform { size: u32 },
+    Storage { writable: bool },
+    /// `kind` is `None` for depth textures.
+    Texture { dimension: TextureViewDimension, kind: Option<ScalarKind>, multisampled: bool },
+    StorageTexture { dimension: TextureViewDimension },
+    Sampler { comparison: bool },
+}
+
+/// A resource a shader declares, with the stages whose entry points use it.
+#[derive(Debug, Clone)]
+pub(in crate::state) struct ShaderBinding {
+    pub(in crate::state) group: u32,
+    pub(in crate::state) binding: u32,
+    pub(in crate::state) name: String,
+    pub(in crate::state) resource: Resource,
+    pub(in crate::state) stages: ShaderStages,
+}
+
+/// An `@location` argument of the vertex entry point.
+#[derive(Debug, Clone)]
+pub(in crate::state) struct VertexInput {
+    pub(in crate::state) location: u32,
+    pub(in crate::state) name: String,
+    pub(in crate::state) kind: ScalarKind,
+    pub(in crate::state) components: u32,
+}
+
+/// Bindings and vertex inputs of a shader, read from its naga modules.
+#[derive(Debug, Clone, Default)]
+pub(in crate::state) struct Reflection {
+    bindings: Vec<ShaderBinding>,
+    vertex_inputs: Vec<VertexInput>,
+}
+
+fn stage_flags(stage: ShaderStage) -> ShaderStages {
+    match stage {
+        ShaderStage::Vertex => ShaderStages::VERTEX,
+        ShaderStage::Fragment => ShaderStages::FRAGMENT,
+        ShaderStage::Compute => ShaderStages::COMPUTE,
+    }
+}
+
+fn view_dimension(dim: ImageDimension, arrayed: bool) -> TextureViewDimension {
+    match (dim, arrayed) {
+        (ImageDimension::D1, _) => TextureViewDimension::D1,
+        (ImageDimension::D2, false) => TextureViewDimension::D2,
+        (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+        (ImageDimension::D3, _) => TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+    }
+}
+
+fn dimension_name(dimension: TextureViewDimension) -> &'static str {
+    match dimension {
+        TextureViewDimension::D1 => "1D",
+        TextureViewDimension::D2 => "2D",
+        TextureViewDimension::D2Array => "2D array",
+        TextureViewDimension::Cube => "cube",
+        TextureViewDimension::CubeArray => "cube array",
+        TextureViewDimension::D3 => "3D",
+    }
+}
+
+fn kind_name(kind: ScalarKind) -> &'static str {
+    match kind {
+        ScalarKind::Float => "f32",
+        ScalarKind::Sint => "i32",
+        ScalarKind::Uint => "u32",
+        ScalarKind::Bool => "bool",
+    }
+}
+
+fn stage_names(stages: ShaderStages) -> String {
+    let names: Vec<_> = [(ShaderStages::VERTEX, "vertex"), (ShaderStages::FRAGMENT, "fragment"), (ShaderStages::COMPUTE, "compute")]
+        .into_iter()
+        .filter(|(stage, _)| stages.contains(*stage))
+        .map(|(_, name)| name)
+        .collect();
+    if names.is_empty() { "no stage".to_string() } else { names.join(" and ") }
+}
+
+// scalar kind and component count a vertex format reaches the shader as
+fn format_type(format: VertexFormat) -> (ScalarKind, u32) {
+    use VertexFormat::*;
+    match format {
+        Uint8x2 | Uint16x2 | Uint32x2 => (ScalarKind::Uint, 2),
+        Uint8x4 | Uint16x4 | Uint32x4 => (ScalarKind::Uint, 4),
+        Uint32 => (ScalarKind::Uint, 1),
+        Uint32x3 => (ScalarKind::Uint, 3),
+        Sint8x2 | Sint16x2 | Sint32x2 => (ScalarKind::Sint, 2),
+        Sint8x4 | Sint16x4 | Sint32x4 => (ScalarKind::Sint, 4),
+        Sint32 => (ScalarKind::Sint, 1),
+        Sint32x3 => (ScalarKind::Sint, 3),
+        Float32 | Float64 => (ScalarKind::Float, 1),
+        Unorm8x2 | Snorm8x2 | Unorm16x2 | Snorm16x2 | Float16x2 | Float32x2 | Float64x2 => (ScalarKind::Float, 2),
+        Float32x3 | Float64x3 => (ScalarKind::Float, 3),
+        Unorm8x4 | Snorm8x4 | Unorm16x4 | Snorm16x4 | Float16x4 | Float32x4 | Float64x4 => (ScalarKind::Float, 4),
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uniform { size } => write!(f, "uniform buffer of {} bytes", size),
+            Self::Storage { writable: true } => write!(f, "read-write storage buffer"),
+            Self::Storage { writable: false } => write!(f, "read-only storage buffer"),
+            Self::Texture { dimension, kind, multisampled } => write!(f, "{}{} {} texture",
+                if *multisampled { "multisampled " } else { "" }, dimension_name(*dimension), kind.map_or("depth", kind_name)),
+            Self::StorageTexture { dimension } => write!(f, "{} storage texture", dimension_name(*dimension)),
+            Self::Sampler { comparison: true } => write!(f, "comparison sampler"),
+            Self::Sampler { comparison: false } => write!(f, "sampler"),
+        }
+    }
+}
+
+// the layout side of a mismatch, worded like `Resource`
+fn describe_binding_type(ty: &BindingType) -> String {
+    match ty {
+        BindingType::Buffer { ty: BufferBindingType::Uniform, min_binding_size: Some(size), .. } => format!("uniform buffer of at least {} bytes", size),
+        BindingType::Buffer { ty: BufferBindingType::Uniform, min_binding_size: None, .. } => "uniform buffer".to_string(),
+        BindingType::Buffer { ty: BufferBindingType::Storage { read_only: true }, .. } => "read-only storage buffer".to_string(),
+        BindingType::Buffer { ty: BufferBindingType::Storage { read_only: false }, .. } => "read-write storage buffer".to_string(),
+        BindingType::Sampler(SamplerBindingType::Comparison) => "comparison sampler".to_string(),
+        BindingType::Sampler(_) => "sampler".to_string(),
+        BindingType::Texture { sample_type, view_dimension, multisampled } => format!("{}{} {} texture",
+            if *multisampled { "multisampled " } else { "" }, dimension_name(*view_dimension), match sample_type {
+                TextureSampleType::Float { .. } => "f32",
+                TextureSampleType::Sint => "i32",
+                TextureSampleType::Uint => "u32",
+                TextureSampleType::Depth => "depth",
+            }),
+        BindingType::StorageTexture { view_dimension, .. } => format!("{} storage texture", dimension_name(*view_dimension)),
+    }
+}
+
+impl Resource {
+    fn matches(&self, ty: &BindingType) -> bool {
+        match (self, ty) {
+            (Self::Uniform { size }, BindingType::Buffer { ty: BufferBindingType::Uniform, min_binding_size, .. }) =>
+                min_binding_size.is_none_or(|min| min.get() >= *size as u64),
+            (Self::Storage { writable }, BindingType::Buffer { ty: BufferBindingType::Storage { read_only }, .. }) =>
+                !(*writable && *read_only),
+            (Self::Texture { dimension, kind, multisampled }, BindingType::Texture { sample_type, view_dimension, multisampled: layout_multisampled }) =>
+                dimension == view_dimension && multisampled == layout_multisampled && matches!((kind, sample_type),
+                    (Some(ScalarKind::Float), TextureSampleType::Float { .. } | TextureSampleType::Depth)
+                    | (Some(ScalarKind::Sint), TextureSampleType::Sint)
+                    | (Some(ScalarKind::Uint), TextureSampleType::Uint)
+                    | (None, TextureSampleType::Depth)),
+            (Self::StorageTexture { dimension }, BindingType::StorageTexture { view_dimension, .. }) => dimension == view_dimension,
+            (Self::Sampler { comparison }, BindingType::Sampler(sampler)) => *comparison == (*sampler == SamplerBindingType::Comparison),
+            _ => false,
+        }
+    }
+}
+
+impl Reflection {
+    /// Reflects `modules` together, as the stages of one pipeline. Vertex inputs come from the first vertex entry point.
+    pub(in crate::state) fn new(modules: &[&Module]) -> Result<Self> {
+        let mut bindings: BTreeMap<(u32, u32), ShaderBinding> = BTreeMap::new();
+        let mut vertex_inputs = None;
+
+        for module in modules {
+            // validation also tells which globals each entry point uses
+            let info = Validator::new(ValidationFlags::all(), Capabilities::all()).validate(module)?;
+            let mut layouter = Layouter::default();
+            layouter.update(&module.types, &module.constants)?;
+
+            for (handle, global) in module.global_variables.iter() {
+                let Some(binding) = &global.binding else {
+                    continue;
+                };
+                let resource = match (global.space, &module.types[global.ty].inner) {
+                    (AddressSpace::Uniform, _) => Resource::Uniform { size: layouter[global.ty].size },
+                    (AddressSpace::Storage { access }, _) => Resource::Storage { writable: access.contains(StorageAccess::STORE) },
+                    (_, TypeInner::Image { dim, arrayed, class }) => {
+                        let dimension = view_dimension(*dim, *arrayed);
+                        match class {
+                            ImageClass::Sampled { kind, multi } => Resource::Texture { dimension, kind: Some(*kind), multisampled: *multi },
+                            ImageClass::Depth { multi } => Resource::Texture { dimension, kind: None, multisampled: *multi },
+                            ImageClass::Storage { .. } => Resource::StorageTexture { dimension },
+                        }
+                    }
+                    (_, TypeInner::Sampler { comparison }) => Resource::Sampler { comparison: *comparison },
+                    _ => continue,
+                };
+                let stages = module.entry_points.iter().enumerate()
+                    .filter(|(index, _)| !info.get_entry_point(*index)[handle].is_empty())
+                    .fold(ShaderStages::NONE, |stages, (_, entry)| stages | stage_flags(entry.stage));
+
+                bindings.entry((binding.group, binding.binding))
+                    .and_modify(|existing| existing.stages |= stages)
+                    .or_insert_with(|| ShaderBinding {
+                        group: binding.group,
+                        binding: binding.binding,
+                        name: global.name.clone().unwrap_or_default(),
+                        resource,
+                        stages,
+                    });
+            }
+
+            if vertex_inputs.is_none() {
+                if let Some(entry) = module.entry_points.iter().find(|entry| entry.stage == ShaderStage::Vertex) {
+                    vertex_inputs = Some(Self::reflect_vertex_inputs(module, &entry.function));
+                }
+            }
+        }
+
+        Ok(Self {
+            bindings: bindings.into_values().collect(),
+            vertex_inputs: vertex_inputs.unwrap_or_default(),
+        })
+    }
+
+    // `@location` arguments, directly or as members of struct arguments
+    fn reflect_vertex_inputs(module: &Module, function: &naga::Function) -> Vec<VertexInput> {
+        let mut inputs = Vec::new();
+        let mut push = |name: &Option<String>, ty, binding: &Option<Binding>| {
+            let Some(Binding::Location { location, .. }) = binding else {
+                return;
+            };
+            let (kind, components) = match module.types[ty].inner {
+                TypeInner::Scalar { kind, .. } => (kind, 1),
+                TypeInner::Vector { size, kind, .. } => (kind, size as u32),
+                _ => return,
+            };
+            inputs.push(VertexInput { location: *location, name: name.clone().unwrap_or_default(), kind, components });
+        };
+        for argument in &function.arguments {
+            match &module.types[argument.ty].inner {
+                TypeInner::Struct { members, .. } => {
+                    for member in members {
+                        push(&member.name, member.ty, &member.binding);
+                    }
+                }
+                _ => push(&argument.name, argument.ty, &argument.binding),
+            }
+        }
+        inputs.sort_by_key(|input| input.location);
+        inputs
+    }
+
+    /// Checks every binding the entry points use against `groups`, the layout entries of each bind group
+    /// in order, and the vertex inputs against `vertex_buffers`. Lists every mismatch in the error.
+    pub(in crate::state) fn validate(&self, groups: &[&[BindGroupLayoutEntry]], vertex_buffers: &[VertexBufferLayout]) -> Result<()> {
+        let mut problems = Vec::new();
+
+        for binding in self.bindings.iter().filter(|binding| !binding.stages.is_empty()) {
+            let name = format!("`{}` at @group({}) @binding({})", binding.name, binding.group, binding.binding);
+            let Some(entries) = groups.get(binding.group as usize) else {
+                problems.push(format!("{} is in a group the pipeline doesn't have, it has {} groups", name, groups.len()));
+                continue;
+            };
+            let Some(entry) = entries.iter().find(|entry| entry.binding == binding.binding) else {
+                let available: Vec<_> = entries.iter().map(|entry| entry.binding.to_string()).collect();
+                problems.push(format!("{} isn't in the layout, group {} has bindings [{}]", name, binding.group, available.join(", ")));
+                continue;
+            };
+            if !binding.resource.matches(&entry.ty) {
+                problems.push(format!("{} is a {}, but the layout has a {} there", name, binding.resource, describe_binding_type(&entry.ty)));
+            }
+            let hidden = binding.stages - entry.visibility;
+            if !hidden.is_empty() {
+                problems.push(format!("{} is used by the {} stage, but the layout only makes it visible to the {} stage",
+                                      name, stage_names(hidden), stage_names(entry.visibility)));
+            }
+        }
+
+        let attributes: Vec<_> = vertex_buffers.iter().flat_map(|buffer| buffer.attributes).collect();
+        for input in &self.vertex_inputs {
+            let name = format!("vertex input `{}` at @location({})", input.name, input.location);
+            let Some(attribute) = attributes.iter().find(|attribute| attribute.shader_location == input.location) else {
+                let available: Vec<_> = attributes.iter().map(|attribute| attribute.shader_location.to_string()).collect();
+                problems.push(format!("{} isn't provided, the vertex buffers have locations [{}]", name, available.join(", ")));
+                continue;
+            };
+            // the shader may declare more components than the format has, missing ones are filled in
+            let (kind, components) = format_type(attribute.format);
+            if kind != input.kind || components > input.components {
+                problems.push(format!("{} is {}, but the vertex buffer provides {:?}", name, input, attribute.format));
+            }
+        }
+
+        if !problems.is_empty() {
+            bail!("shader doesn't match the pipeline layout:\n  {}", problems.join("\n  "));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for VertexInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.components {
+            1 => write!(f, "{}", kind_name(self.kind)),
+            components => write!(f, "vec{}<{}>", components, kind_name(self.kind)),
+        }
+    }
+}
+
+impl fmt::Display for Reflection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "bindings:")?;
+        for binding in &self.bindings {
+            writeln!(f, "  @group({}) @binding({}) {}: {}, used by {}", binding.group, binding.binding, binding.name,
+                     binding.resource, stage_names(binding.stages))?;
+        }
+        writeln!(f, "vertex inputs:")?;
+        for input in &self.vertex_inputs {
+            writeln!(f, "  @location({}) {}: {}", input.location, input.name, input)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use std::num::NonZeroU64;
+use naga::{front::wgsl, ScalarKind};
+use wgpu::{
+    BindGroupLayoutEntry, BindingType, BufferAddress, BufferBindingType, SamplerBindingType, ShaderStages, TextureSampleType,
+    TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode
+};
+use super::{Reflection, Resource};
+
+fn reflect(sources: &[&str]) -> Reflection {
+    let modules: Vec<_> = sources.iter().map(|source| wgsl::parse_str(source).unwrap_or_else(|error| panic!("{}", error))).collect();
+    Reflection::new(&modules.iter().collect::<Vec<_>>()).unwrap()
+}
+
+// every mismatch `validate` reports, one per line
+fn problems(source: &str, groups: &[&[BindGroupLayoutEntry]], vertex_buffers: &[VertexBufferLayout]) -> Vec<String> {
+    match reflect(&[source]).validate(groups, vertex_buffers) {
+        Ok(()) => Vec::new(),
+        Err(error) => error.to_string().lines().skip(1).map(|line| line.trim().to_string()).collect(),
+    }
+}
+
+fn entry(binding: u32, visibility: ShaderStages, ty: BindingType) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry { binding, visibility, ty, count: None }
+}
+
+fn uniform(min_binding_size: Option<u64>) -> BindingType {
+    BindingType::Buffer { ty: BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: min_binding_size.and_then(NonZeroU64::new) }
+}
+
+fn storage(read_only: bool) -> BindingType {
+    BindingType::Buffer { ty: BufferBindingType::Storage { read_only }, has_dynamic_offset: false, min_binding_size: None }
+}
+
+fn texture(sample_type: TextureSampleType, view_dimension: TextureViewDimension, multisampled: bool) -> BindingType {
+    BindingType::Texture { sample_type, view_dimension, multisampled }
+}
+
+const FLOAT: TextureSampleType = TextureSampleType::Float { filterable: true };
+
+fn vertex_buffer(attributes: &[VertexAttribute]) -> VertexBufferLayout<'_> {
+    VertexBufferLayout { array_stride: 64 as BufferAddress, step_mode: VertexStepMode::Vertex, attributes }
+}
+
+fn attribute(shader_location: u32, format: VertexFormat) -> VertexAttribute {
+    VertexAttribute { format, offset: 0, shader_location }
+}
+
+const UNIFORMS: &str = "
+struct Globals { color: vec4<f32>, time: f32 }
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var unused_texture: texture_2d<f32>;
+@group(1) @binding(0) var color_texture: texture_2d<f32>;
+@group(1) @binding(1) var color_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(f32(index), globals.time, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return globals.color * textureSample(color_texture, color_sampler, position.xy);
+}
+";
+
+#[test]
+fn bindings_know_their_resource_and_stages() {
+    let reflection = reflect(&[UNIFORMS]);
+    let bindings: Vec<_> = reflection.bindings.iter()
+        .map(|binding| (binding.group, binding.binding, binding.name.as_str(), binding.resource.clone(), binding.stages))
+        .collect();
+    assert_eq!(bindings, [
+        // vec4 and f32 pad to 32 bytes
+        (0, 0, "globals", Resource::Uniform { size: 32 }, ShaderStages::VERTEX_FRAGMENT),
+        (0, 1, "unused_texture", Resource::Texture { dimension: TextureViewDimension::D2, kind: Some(ScalarKind::Float), multisampled: false }, ShaderStages::NONE),
+        (1, 0, "color_texture", Resource::Texture { dimension: TextureViewDimension::D2, kind: Some(ScalarKind::Float), multisampled: false }, ShaderStages::FRAGMENT),
+        (1, 1, "color_sampler", Resource::Sampler { comparison: false }, ShaderStages::FRAGMENT),
+    ]);
+}
+
+#[test]
+fn stages_of_separate_modules_are_merged() {
+    let vertex = "
+        @group(0) @binding(0) var<uniform> offset: vec4<f32>;
+        @vertex
+        fn vs_main() -> @builtin(position) vec4<f32> { return offset; }
+    ";
+    let fragment = "
+        @group(0) @binding(0) var<uniform> offset: vec4<f32>;
+        @fragment
+        fn fs_main() -> @location(0) vec4<f32> { return offset; }
+    ";
+    let reflection = reflect(&[vertex, fragment]);
+    assert_eq!(reflection.bindings.len(), 1);
+    assert_eq!(reflection.bindings[0].stages, ShaderStages::VERTEX_FRAGMENT);
+}
+
+#[test]
+fn matching_layouts_pass() {
+    let globals = [entry(0, ShaderStages::VERTEX_FRAGMENT, uniform(Some(32)))];
+    let material = [
+        entry(0, ShaderStages::FRAGMENT, texture(FLOAT, TextureViewDimension::D2, false)),
+        entry(1, ShaderStages::FRAGMENT, BindingType::Sampler(SamplerBindingType::Filtering)),
+    ];
+    // the unused texture needn't be in the layout
+    assert_eq!(problems(UNIFORMS, &[&globals, &material], &[]), Vec::<String>::new());
+    // layouts without a minimum size take any uniform
+    let globals = [entry(0, ShaderStages::all(), uniform(None))];
+    assert_eq!(problems(UNIFORMS, &[&globals, &material], &[]), Vec::<String>::new());
+}
+
+#[test]
+fn missing_groups_and_bindings_are_reported() {
+    let globals = [entry(0, ShaderStages::VERTEX_FRAGMENT, uniform(Some(32)))];
+    assert_eq!(problems(UNIFORMS, &[&globals], &[]), [
+        "`color_texture` at @group(1) @binding(0) is in a group the pipeline doesn't have, it has 1 groups",
+        "`color_sampler` at @group(1) @binding(1) is in a group the pipeline doesn't have, it has 1 groups",
+    ]);
+    let material = [entry(1, ShaderStages::FRAGMENT, BindingType::Sampler(SamplerBindingType::Filtering)),
+                    entry(4, ShaderStages::FRAGMENT, BindingType::Sampler(SamplerBindingType::Filtering))];
+    assert_eq!(problems(UNIFORMS, &[&globals, &material], &[]), [
+        "`color_texture` at @group(1) @binding(0) isn't in the layout, group 1 has bindings [1, 4]",
+    ]);
+}
+
+#[test]
+fn small_uniforms_and_hidden_stages_are_reported() {
+    let globals = [entry(0, ShaderStages::FRAGMENT, uniform(Some(16)))];
+    let material = [
+        entry(0, ShaderStages::FRAGMENT, texture(FLOAT, TextureViewDimension::D2, false)),
+        entry(1, ShaderStages::FRAGMENT, BindingType::Sampler(SamplerBindingType::Filtering)),
+    ];
+    assert_eq!(problems(UNIFORMS, &[&globals, &material], &[]), [
+        "`globals` at @group(0) @binding(0) is a uniform buffer of 32 bytes, but the layout has a uniform buffer of at least 16 bytes there",
+        "`globals` at @group(0) @binding(0) is used by the vertex stage, but the layout only makes it visible to the fragment stage",
+    ]);
+}
+
+#[test]
+fn storage_buffers_must_be_writable_when_written() {
+    let source = "
+        @group(0) @binding(0) var<storage, read> input: array<f32>;
+        @group(0) @binding(1) var<storage, read_write> output: array<f32>;
+        @compute @workgroup_size(1)
+        fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) { output[id.x] = input[id.x]; }
+    ";
+    let writable = [entry(0, ShaderStages::COMPUTE, storage(false)), entry(1, ShaderStages::COMPUTE, storage(false))];
+    assert_eq!(problems(source, &[&writable], &[]), Vec::<String>::new());
+    let read_only = [entry(0, ShaderStages::COMPUTE, storage(true)), entry(1, ShaderStages::COMPUTE, storage(true))];
+    assert_eq!(problems(source, &[&read_only], &[]), [
+        "`output` at @group(0) @binding(1) is a read-write storage buffer, but the layout has a read-only storage buffer there",
+    ]);
+}
+
+#[test]
+fn textures_must_agree_on_kind_dimension_and_sampling() {
+    let source = "
+        @group(0) @binding(0) var floats: texture_2d<f32>;
+        @group(0) @binding(1) var ints: texture_2d<i32>;
+        @group(0) @binding(2) var depth: texture_depth_2d;
+        @group(0) @binding(3) var samples: texture_multisampled_2d<f32>;
+        @group(0) @binding(4) var layers: texture_2d_array<u32>;
+        @group(0) @binding(5) var shadow: sampler_comparison;
+        @fragment
+        fn fs_main() -> @location(0) vec4<f32> {
+            let at = vec2<i32>(0, 0);
+            return textureLoad(floats, at, 0) + vec4<f32>(textureLoad(ints, at, 0)) + textureLoad(samples, at, 0)
+                + vec4<f32>(textureLoad(layers, at, 0, 0)) + textureSampleCompare(depth, shadow, vec2<f32>(0.5), 0.5);
+        }
+    ";
+    let matching = [
+        // sampling a depth texture as floats is fine
+        entry(0, ShaderStages::FRAGMENT, texture(TextureSampleType::Depth, TextureViewDimension::D2, false)),
+        entry(1, ShaderStages::FRAGMENT, texture(TextureSampleType::Sint, TextureViewDimension::D2, false)),
+        entry(2, ShaderStages::FRAGMENT, texture(TextureSampleType::Depth, TextureViewDimension::D2, false)),
+        entry(3, ShaderStages::FRAGMENT, texture(FLOAT, TextureViewDimension::D2, true)),
+        entry(4, ShaderStages::FRAGMENT, texture(TextureSampleType::Uint, TextureViewDimension::D2Array, false)),
+        entry(5, ShaderStages::FRAGMENT, BindingType::Sampler(SamplerBindingType::Comparison)),
+    ];
+    assert_eq!(problems(source, &[&matching], &[]), Vec::<String>::new());
+
+    let mismatched = [
+        entry(0, ShaderStages::FRAGMENT, texture(TextureSampleType::Uint, TextureViewDimension::D2, false)),
+        entry(1, ShaderStages::FRAGMENT, texture(FLOAT, TextureViewDimension::D2, false)),
+        entry(2, ShaderStages::FRAGMENT, texture(FLOAT, TextureViewDimension::D2, false)),
+        entry(3, ShaderStages::FRAGMENT, texture(FLOAT, TextureViewDimension::D2, false)),
+        entry(4, ShaderStages::FRAGMENT, texture(TextureSampleType::Uint, TextureViewDimension::D2, false)),
+        entry(5, ShaderStages::FRAGMENT, BindingType::Sampler(SamplerBindingType::Filtering)),
+    ];
+    assert_eq!(problems(source, &[&mismatched], &[]), [
+        "`floats` at @group(0) @binding(0) is a 2D f32 texture, but the layout has a 2D u32 texture there",
+        "`ints` at @group(0) @binding(1) is a 2D i32 texture, but the layout has a 2D f32 texture there",
+        "`depth` at @group(0) @binding(2) is a 2D depth texture, but the layout has a 2D f32 texture there",
+        "`samples` at @group(0) @binding(3) is a multisampled 2D f32 texture, but the layout has a 2D f32 texture there",
+        "`layers` at @group(0) @binding(4) is a 2D array u32 texture, but the layout has a 2D u32 texture there",
+        "`shadow` at @group(0) @binding(5) is a comparison sampler, but the layout has a sampler there",
+    ]);
+}
+
+const VERTEX_INPUTS: &str = "
+struct Vertex {
+    @location(1) uv: vec2<f32>,
+    @location(0) position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(vertex: Vertex, @location(4) id: u32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(vertex.position + vec3<f32>(vertex.uv, f32(id)), 1.0);
+}
+";
+
+#[test]
+fn vertex_inputs_come_from_arguments_and_their_members() {
+    let reflection = reflect(&[VERTEX_INPUTS]);
+    let inputs: Vec<_> = reflection.vertex_inputs.iter().map(|input| (input.location, input.name.as_str(), input.to_string())).collect();
+    assert_eq!(inputs, [(0, "position", "vec3<f32>".to_string()), (1, "uv", "vec2<f32>".to_string()), (4, "id", "u32".to_string())]);
+}
+
+#[test]
+fn vertex_formats_may_have_fewer_components_of_the_same_kind() {
+    let attributes = [attribute(0, VertexFormat::Float32x2), attribute(1, VertexFormat::Unorm8x2)];
+    let instances = [attribute(4, VertexFormat::Uint32)];
+    assert_eq!(problems(VERTEX_INPUTS, &[], &[vertex_buffer(&attributes), vertex_buffer(&instances)]), Vec::<String>::new());
+
+    let attributes = [attribute(0, VertexFormat::Float32x4), attribute(1, VertexFormat::Sint32x2)];
+    assert_eq!(problems(VERTEX_INPUTS, &[], &[vertex_buffer(&attributes)]), [
+        "vertex input `position` at @location(0) is vec3<f32>, but the vertex buffer provides Float32x4",
+        "vertex input `uv` at @location(1) is vec2<f32>, but the vertex buffer provides Sint32x2",
+        "vertex input `id` at @location(4) isn't provided, the vertex buffers have locations [0, 1]",
+    ]);
+}
+
+#[test]
+fn fragment_only_shaders_have_no_vertex_inputs() {
+    let source = "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }";
+    assert!(reflect(&[source]).vertex_inputs.is_empty());
+    assert_eq!(problems(source, &[], &[]), Vec::<String>::new());
+}
@@ -1,33 +1,62 @@
+pub(super) mod readback;
+
 use anyhow::Result;
 use image::{DynamicImage, GenericImageView};
 use std::num::NonZeroU32;
 use wgpu::{
     AddressMode, CompareFunction, Device, Extent3d, FilterMode, ImageCopyTexture,
-    ImageDataLayout, Origin3d, Queue, SamplerDescriptor, SurfaceConfiguration,
+    ImageDataLayout, Origin3d, Queue, SamplerDescriptor,
     TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureUsages, TextureViewDescriptor
+    TextureUsages, TextureView, TextureViewDescriptor
 };
 
-pub(super) struct Texture {
+/// Sampled texture with its view and sampler, bound by [`BundleBuilder::texture`](crate::BundleBuilder::texture).
+#[derive(Debug)]
+pub struct Texture {
     pub(super) texture: wgpu::Texture,
     pub(super) view: wgpu::TextureView,
     pub(super) sampler: wgpu::Sampler,
 }
 
 impl Texture {
+    /// Starts a texture labelled `label`, clamped and sampled with the nearest texel.
+    pub fn builder(label: &str) -> TextureBuilder<'_> {
+        TextureBuilder {
+            label,
+            normal_map: false,
+            address_mode: AddressMode::ClampToEdge,
+            filter: FilterMode::Nearest,
+        }
+    }
+
+    /// The texture itself.
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// View of the whole texture.
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// Sampler the texture is bound with.
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
     pub(super) const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
 
-    pub(super) fn create_depth_texture(device: &Device, config: &SurfaceConfiguration, label: &str) -> Self {
+    pub(super) fn create_depth_texture(device: &Device, (width, height): (u32, u32), sample_count: u32, label: &str) -> Self {
         let size = Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
         let desc = TextureDescriptor {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
             usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
@@ -54,12 +83,95 @@ impl Texture {
         }
     }
 
-    pub(super) fn from_bytes(device: &Device, queue: &Queue, bytes: &[u8], label: &str, is_normal_map: bool) -> Result<Self> {
+    /// Color target drawn into by one pass and sampled pixel for pixel by a later one.
+    pub(super) fn create_render_target(device: &Device, (width, height): (u32, u32), format: TextureFormat, label: &str) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Color target rendered to with `sample_count` samples, then resolved into the surface texture.
+    pub(super) fn create_multisampled_framebuffer(device: &Device, (width, height): (u32, u32), format: TextureFormat,
+                                                  sample_count: u32) -> TextureView {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Multisampled Framebuffer"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+        });
+        texture.create_view(&TextureViewDescriptor::default())
+    }
+}
+
+/// Uploads an image as a [`Texture`], see [`Texture::builder`].
+#[derive(Debug, Clone, Copy)]
+pub struct TextureBuilder<'a> {
+    label: &'a str,
+    normal_map: bool,
+    address_mode: AddressMode,
+    filter: FilterMode,
+}
+
+impl<'a> TextureBuilder<'a> {
+    /// Keeps the texels linear instead of decoding them as sRGB.
+    pub fn normal_map(mut self, normal_map: bool) -> Self {
+        self.normal_map = normal_map;
+        self
+    }
+
+    /// How coordinates outside the texture are sampled, on every axis.
+    pub fn address_mode(mut self, address_mode: AddressMode) -> Self {
+        self.address_mode = address_mode;
+        self
+    }
+
+    /// Filter used when the texture is minified, it's always magnified linearly.
+    pub fn filter(mut self, filter: FilterMode) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Decodes `bytes` in any format the `image` crate was built with.
+    pub fn from_bytes(self, device: &Device, queue: &Queue, bytes: &[u8]) -> Result<Texture> {
         let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, Some(label), is_normal_map)
+        Ok(self.from_image(device, queue, &img))
     }
 
-    pub(super) fn from_image(device: &Device, queue: &Queue, img: &DynamicImage, label: Option<&str>, is_normal_map: bool) -> Result<Self> {
+    /// Uploads `img` as RGBA.
+    pub fn from_image(self, device: &Device, queue: &Queue, img: &DynamicImage) -> Texture {
         let dimensions = img.dimensions();
         let rgba = img.to_rgba8();
 
@@ -69,12 +181,12 @@ impl Texture {
             depth_or_array_layers: 1,
         };
         let texture = device.create_texture(&TextureDescriptor {
-            label,
+            label: Some(self.label),
             size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: if is_normal_map { TextureFormat::Rgba8Unorm } else { TextureFormat::Rgba8UnormSrgb },
+            format: if self.normal_map { TextureFormat::Rgba8Unorm } else { TextureFormat::Rgba8UnormSrgb },
             usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
         });
 
@@ -96,19 +208,19 @@ impl Texture {
 
         let view = texture.create_view(&TextureViewDescriptor::default());
         let sampler = device.create_sampler(&SamplerDescriptor {
-            address_mode_u: AddressMode::ClampToEdge,
-            address_mode_v: AddressMode::ClampToEdge,
-            address_mode_w: AddressMode::ClampToEdge,
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
             mag_filter: FilterMode::Linear,
-            min_filter: FilterMode::Nearest,
+            min_filter: self.filter,
             mipmap_filter: FilterMode::Nearest,
             ..Default::default()
         });
 
-        Ok(Self {
+        Texture {
             texture,
             view,
             sampler,
-        })
+        }
     }
-}
\ No newline at end of file
+}
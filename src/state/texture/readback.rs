@@ -0,0 +1,132 @@
+use std::{
+    future::Future,
+    num::NonZeroU32,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker}
+};
+use anyhow::{anyhow, bail, Result};
+use image::RgbaImage;
+use wgpu::{
+    BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Extent3d,
+    ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, MapMode, Origin3d, Queue, TextureAspect,
+    TextureFormat, COPY_BYTES_PER_ROW_ALIGNMENT
+};
+
+#[derive(Debug, Default)]
+struct Mapping {
+    result: Option<Result<(), BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// Pixels of a texture on their way back from the GPU, resolved once the copy is mapped.
+///
+/// Natively the device has to be polled for the mapping to finish, browsers finish it on their own.
+#[derive(Debug)]
+pub(in crate::state) struct Readback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+    mapping: Arc<Mutex<Mapping>>,
+}
+
+impl Readback {
+    /// Copies `texture`, which needs `COPY_SRC` usage and an 8 bit RGBA or BGRA format, into a mappable buffer.
+    pub(in crate::state) fn new(device: &Device, queue: &Queue, texture: &wgpu::Texture, format: TextureFormat,
+                                (width, height): (u32, u32)) -> Result<Self> {
+        let bgra = match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            _ => bail!("can't read back {:?} textures, only 8 bit RGBA and BGRA", format),
+        };
+        // rows of a buffer copy are aligned to 256 bytes
+        let padded_bytes_per_row = (4 * width).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: Some("Readback Encoder") });
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                aspect: TextureAspect::All,
+                texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+            },
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+            },
+            Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let mapping = Arc::new(Mutex::new(Mapping::default()));
+        let callback = Arc::clone(&mapping);
+        buffer.slice(..).map_async(MapMode::Read, move |result| {
+            let mut mapping = callback.lock().unwrap();
+            mapping.result = Some(result);
+            if let Some(waker) = mapping.waker.take() {
+                waker.wake();
+            }
+        });
+
+        Ok(Self { buffer, width, height, padded_bytes_per_row, bgra, mapping })
+    }
+
+    /// The pixels if the mapping is done, without waiting for it.
+    pub(in crate::state) fn try_take(&self) -> Option<Result<RgbaImage>> {
+        let result = self.mapping.lock().unwrap().result.take()?;
+        Some(self.finish(result))
+    }
+
+    fn finish(&self, result: Result<(), BufferAsyncError>) -> Result<RgbaImage> {
+        match result {
+            Ok(()) => self.take_image(),
+            Err(error) => Err(anyhow!("couldn't map the readback buffer: {}", error)),
+        }
+    }
+
+    // strips the row padding and swizzles BGRA into RGBA
+    fn take_image(&self) -> Result<RgbaImage> {
+        let mut pixels = Vec::with_capacity((4 * self.width * self.height) as usize);
+        {
+            let mapped = self.buffer.slice(..).get_mapped_range();
+            for row in mapped.chunks_exact(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..(4 * self.width) as usize]);
+            }
+        }
+        self.buffer.unmap();
+        if self.bgra {
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+        RgbaImage::from_raw(self.width, self.height, pixels).ok_or_else(|| anyhow!("readback has the wrong size"))
+    }
+}
+
+impl Future for Readback {
+    type Output = Result<RgbaImage>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = {
+            let mut mapping = self.mapping.lock().unwrap();
+            match mapping.result.take() {
+                Some(result) => result,
+                None => {
+                    mapping.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        };
+        Poll::Ready(self.finish(result))
+    }
+}
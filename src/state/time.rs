@@ -15,8 +15,8 @@ impl Instant {
     pub fn now() -> Self { Self(std::time::Instant::now()) }
     pub fn duration_since(&self, earlier: Instant) -> Duration { self.0.duration_since(earlier.0) }
     pub fn elapsed(&self) -> Duration { self.0.elapsed() }
-    pub fn checked_add(&self, duration: Duration) -> Option<Self> { self.0.checked_add(duration).map(|i| Self(i)) }
-    pub fn checked_sub(&self, duration: Duration) -> Option<Self> { self.0.checked_sub(duration).map(|i| Self(i)) }
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> { self.0.checked_add(duration).map(Self) }
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> { self.0.checked_sub(duration).map(Self) }
 }
 
 #[cfg(target_arch = "wasm32")]
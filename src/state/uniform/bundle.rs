@@ -0,0 +1,143 @@
+use std::rc::Rc;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBindingType, Device,
+    SamplerBindingType, ShaderStages,
+    TextureSampleType, TextureViewDimension
+};
+use crate::state::{pipeline::cache::PipelineCache, texture::Texture};
+use super::{Uniform, UniformBinding};
+
+/// Several uniforms and textures sharing one bind group, numbered from `@binding(0)` in the order they were added.
+#[derive(Debug)]
+pub struct BindGroupBundle {
+    layout: Rc<BindGroupLayout>,
+    bind_group: BindGroup,
+}
+
+impl BindGroupBundle {
+    /// Starts a bundle labelled `label`.
+    pub fn builder<'a>(label: &'a str) -> BundleBuilder<'a> {
+        BundleBuilder {
+            label,
+            entries: Vec::new(),
+            resources: Vec::new(),
+        }
+    }
+
+    /// Layout of [`bind_group`](Self::bind_group).
+    pub fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    /// Bind group with everything in the bundle.
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+}
+
+// layout entries added by the builder, numbered when they're pushed
+pub(in crate::state) fn storage_entry(visibility: ShaderStages, read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub(in crate::state) fn uniform_entry(visibility: ShaderStages) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub(in crate::state) fn texture_entries(visibility: ShaderStages) -> [BindGroupLayoutEntry; 2] {
+    [
+        BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        },
+    ]
+}
+
+/// Collects the entries of a [`BindGroupBundle`], see [`BindGroupBundle::builder`].
+pub struct BundleBuilder<'a> {
+    label: &'a str,
+    entries: Vec<BindGroupLayoutEntry>,
+    resources: Vec<BindingResource<'a>>,
+}
+
+impl<'a> BundleBuilder<'a> {
+    fn push(&mut self, entry: BindGroupLayoutEntry, resource: BindingResource<'a>) {
+        let binding = self.entries.len() as u32;
+        self.entries.push(BindGroupLayoutEntry { binding, ..entry });
+        self.resources.push(resource);
+    }
+
+    /// Adds the buffer of `uniform`, with the visibility its type describes.
+    pub fn uniform<T: Uniform>(mut self, uniform: &'a UniformBinding<T>) -> Self {
+        self.push(uniform.uniform().get_layout_entry(), uniform.buffer().as_entire_binding());
+        self
+    }
+
+    /// Adds a uniform buffer whose layout isn't described by a [`Uniform`].
+    pub fn uniform_buffer(mut self, buffer: &'a Buffer, visibility: ShaderStages) -> Self {
+        self.push(uniform_entry(visibility), buffer.as_entire_binding());
+        self
+    }
+
+    /// Adds a storage buffer, writable by shaders unless `read_only`.
+    pub fn storage(mut self, buffer: &'a Buffer, visibility: ShaderStages, read_only: bool) -> Self {
+        self.push(storage_entry(visibility, read_only), buffer.as_entire_binding());
+        self
+    }
+
+    /// Adds the view of `texture` followed by its sampler.
+    pub fn texture(mut self, texture: &'a Texture, visibility: ShaderStages) -> Self {
+        let [view, sampler] = texture_entries(visibility);
+        self.push(view, BindingResource::TextureView(&texture.view));
+        self.push(sampler, BindingResource::Sampler(&texture.sampler));
+        self
+    }
+
+    pub(in crate::state) fn build(self, device: &Device, cache: &mut PipelineCache) -> BindGroupBundle {
+        let layout = cache.bind_group_layout(device, &BindGroupLayoutDescriptor {
+            label: Some(self.label),
+            entries: &self.entries,
+        });
+        let entries: Vec<_> = self.resources.into_iter().enumerate()
+            .map(|(binding, resource)| BindGroupEntry { binding: binding as u32, resource })
+            .collect();
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(self.label),
+            layout: &layout,
+            entries: &entries,
+        });
+
+        BindGroupBundle { layout, bind_group }
+    }
+}
@@ -1,24 +1,26 @@
 use bytemuck::{Pod, Zeroable};
 use cgmath::{Matrix4, SquareMatrix};
+use wgsl_layout::wgsl_layout;
 use crate::state::camera::{Camera, Projection};
 
+#[wgsl_layout(name = "Camera")]
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 pub(in crate::state) struct CameraUniform {
-    view_position: [f32; 4],
+    view_pos: [f32; 4],
     view_proj: [[f32; 4]; 4],
 }
 
 impl CameraUniform {
     pub(in crate::state) fn new() -> Self {
         Self {
-            view_position: [0.0; 4],
+            view_pos: [0.0; 4],
             view_proj: Matrix4::identity().into(),
         }
     }
 
     pub(in crate::state) fn update_view_proj(&mut self, camera: &Camera, projection: &Projection) {
-        self.view_position = camera.position.to_homogeneous().into();
+        self.view_pos = camera.position.to_homogeneous().into();
         self.view_proj = (projection.calc_matrix() * camera.calc_matrix()).into();
     }
 }
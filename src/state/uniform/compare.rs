@@ -0,0 +1,27 @@
+use bytemuck::{Pod, Zeroable};
+use wgsl_layout::wgsl_layout;
+use crate::state::compare::CompareMode;
+
+#[wgsl_layout(name = "Compare")]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(in crate::state) struct CompareUniform {
+    divider: f32,
+    difference: u32,
+    gain: f32,
+    line_width: f32,
+}
+
+impl CompareUniform {
+    /// `divider` is a fraction of the width, where the compared shader starts.
+    pub(in crate::state) fn new(divider: f32, mode: CompareMode) -> Self {
+        let mut uniform = Self { gain: 4.0, line_width: 2.0, ..Self::zeroed() };
+        uniform.update(divider, mode);
+        uniform
+    }
+
+    pub(in crate::state) fn update(&mut self, divider: f32, mode: CompareMode) {
+        self.divider = divider;
+        self.difference = (mode == CompareMode::Difference) as u32;
+    }
+}
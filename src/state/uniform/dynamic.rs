@@ -0,0 +1,160 @@
+use std::{num::NonZeroU64, ops::Range, rc::Rc};
+use bytemuck::Pod;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferAddress, BufferBinding,
+    BufferDescriptor, BufferUsages, Device, DynamicOffset, Queue
+};
+use crate::state::pipeline::cache::PipelineCache;
+use super::{Flush, Uniform};
+
+/// Many uniforms of one type in a single buffer, each bound by passing [`offset`](Self::offset)
+/// as the dynamic offset of its bind group. Slots are padded to the device's offset alignment.
+#[derive(Debug)]
+pub struct DynamicUniformBuffer<T> {
+    uniforms: Vec<T>,
+    stride: BufferAddress,
+    capacity: usize,
+    buffer: Buffer,
+    bind_group_layout: Rc<BindGroupLayout>,
+    bind_group: BindGroup,
+    // slots changed since the last upload
+    dirty: Option<Range<usize>>,
+}
+
+impl<T: Uniform + Pod> DynamicUniformBuffer<T> {
+    /// Allocates room for `capacity` uniforms, more are made room for as they are pushed.
+    pub(in crate::state) fn new(device: &Device, cache: &mut PipelineCache, capacity: usize) -> Self {
+        let prototype = T::zeroed();
+        let entry = prototype.get_layout_entry();
+        let bind_group_layout = cache.bind_group_layout(device, &BindGroupLayoutDescriptor {
+            label: Some(prototype.get_layout_label()),
+            entries: &[BindGroupLayoutEntry {
+                ty: match entry.ty {
+                    BindingType::Buffer { ty, min_binding_size, .. } => BindingType::Buffer { ty, has_dynamic_offset: true, min_binding_size },
+                    ty => ty,
+                },
+                ..entry
+            }],
+        });
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as BufferAddress;
+        let stride = (std::mem::size_of::<T>() as BufferAddress).div_ceil(alignment) * alignment;
+        let capacity = capacity.max(1);
+        let (buffer, bind_group) = Self::allocate(device, &bind_group_layout, stride, capacity);
+
+        Self {
+            uniforms: Vec::with_capacity(capacity),
+            stride,
+            capacity,
+            buffer,
+            bind_group_layout,
+            bind_group,
+            dirty: None,
+        }
+    }
+
+    fn allocate(device: &Device, layout: &BindGroupLayout, stride: BufferAddress, capacity: usize) -> (Buffer, BindGroup) {
+        let prototype = T::zeroed();
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some(prototype.get_buffer_label()),
+            size: stride * capacity as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(prototype.get_bind_group_label()),
+            layout,
+            entries: &[BindGroupEntry {
+                binding: prototype.get_binding(),
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: NonZeroU64::new(std::mem::size_of::<T>() as u64),
+                }),
+            }],
+        });
+        (buffer, bind_group)
+    }
+
+    /// Appends `uniform` and returns its index, growing the buffer when it's full.
+    /// Growing replaces the bind group, so fetch it again afterwards.
+    pub fn push(&mut self, device: &Device, uniform: T) -> usize {
+        if self.uniforms.len() == self.capacity {
+            self.capacity *= 2;
+            (self.buffer, self.bind_group) = Self::allocate(device, &self.bind_group_layout, self.stride, self.capacity);
+            self.mark_dirty(0..self.uniforms.len());
+        }
+        let index = self.uniforms.len();
+        self.uniforms.push(uniform);
+        self.mark_dirty(index..index + 1);
+        index
+    }
+}
+
+impl<T> DynamicUniformBuffer<T> {
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
+    }
+
+    /// How many uniforms there are.
+    pub fn len(&self) -> usize {
+        self.uniforms.len()
+    }
+
+    /// Whether there are no uniforms.
+    pub fn is_empty(&self) -> bool {
+        self.uniforms.is_empty()
+    }
+
+    /// The uniform at `index`, as it was last changed.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.uniforms.get(index)
+    }
+
+    /// Marks the uniform to be uploaded by the next [`Flush::flush`].
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.uniforms.len() {
+            self.mark_dirty(index..index + 1);
+        }
+        self.uniforms.get_mut(index)
+    }
+
+    /// Removes every uniform, keeping the allocation.
+    pub fn clear(&mut self) {
+        self.uniforms.clear();
+        self.dirty = None;
+    }
+
+    /// Dynamic offset selecting the uniform at `index` in [`bind_group`](Self::bind_group).
+    pub fn offset(&self, index: usize) -> DynamicOffset {
+        (index as BufferAddress * self.stride) as DynamicOffset
+    }
+
+    /// Layout of [`bind_group`](Self::bind_group).
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Bind group with the whole buffer, one uniform at a time through [`offset`](Self::offset).
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+}
+
+impl<T: Pod> Flush for DynamicUniformBuffer<T> {
+    // one write covering every slot between the first and last change
+    fn flush(&mut self, queue: &Queue) -> bool {
+        let Some(dirty) = self.dirty.take() else {
+            return false;
+        };
+        let mut bytes = vec![0; (dirty.len() as BufferAddress * self.stride) as usize];
+        for (slot, uniform) in bytes.chunks_exact_mut(self.stride as usize).zip(&self.uniforms[dirty.clone()]) {
+            slot[..std::mem::size_of::<T>()].copy_from_slice(bytemuck::bytes_of(uniform));
+        }
+        queue.write_buffer(&self.buffer, dirty.start as BufferAddress * self.stride, &bytes);
+        true
+    }
+}
@@ -0,0 +1,67 @@
+use bytemuck::{Pod, Zeroable};
+use serde::Deserialize;
+use wgsl_layout::wgsl_layout;
+
+/// Lights a scene can hold, the size of the array in `lights.wgsl`.
+pub(in crate::state) const MAX_LIGHTS: usize = 4;
+
+/// A point light fading out towards `range`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(in crate::state) struct LightDescriptor {
+    pub(in crate::state) position: [f32; 3],
+    #[serde(default = "default_color")]
+    pub(in crate::state) color: [f32; 3],
+    #[serde(default = "default_intensity")]
+    pub(in crate::state) intensity: f32,
+    #[serde(default = "default_range")]
+    pub(in crate::state) range: f32,
+}
+
+fn default_color() -> [f32; 3] {
+    [1.0; 3]
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_range() -> f32 {
+    20.0
+}
+
+#[wgsl_layout]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(in crate::state) struct Light {
+    position: [f32; 3],
+    intensity: f32,
+    color: [f32; 3],
+    range: f32,
+}
+
+// the array isn't something `wgsl_layout` lays out, `lights.wgsl` declares it by hand
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(in crate::state) struct LightUniform {
+    lights: [Light; MAX_LIGHTS],
+    count: u32,
+    _padding: [u32; 3],
+}
+
+impl LightUniform {
+    /// Keeps the first [`MAX_LIGHTS`] of `lights`.
+    pub(in crate::state) fn new(lights: &[LightDescriptor]) -> Self {
+        let mut uniform = Self::zeroed();
+        for (light, desc) in uniform.lights.iter_mut().zip(lights) {
+            *light = Light {
+                position: desc.position,
+                intensity: desc.intensity,
+                color: desc.color,
+                range: desc.range,
+            };
+        }
+        uniform.count = lights.len().min(MAX_LIGHTS) as u32;
+        uniform
+    }
+}
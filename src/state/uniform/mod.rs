@@ -1,66 +1,149 @@
 pub(super) mod system;
 pub(super) mod camera;
+pub(super) mod light;
+pub(super) mod params;
+pub(super) mod compare;
+pub(crate) mod bundle;
+pub(crate) mod dynamic;
 
+use std::{num::NonZeroU64, rc::Rc};
 use bytemuck::Pod;
 use wgpu::{
-    BindGroup, BindGroupDescriptor, BindGroupEntry,
-    BindGroupLayout, Buffer, BufferUsages, Device,
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, Device, Queue, ShaderStages,
     util::{BufferInitDescriptor, DeviceExt}
 };
+use crate::state::pipeline::cache::PipelineCache;
 
+/// A uniform kept on the CPU, its buffer and a bind group holding just that buffer.
 #[derive(Debug)]
-pub(super) struct UniformBinding<T> {
+pub struct UniformBinding<T> {
     uniform: T,
     buffer: Buffer,
+    bind_group_layout: Rc<BindGroupLayout>,
     bind_group: BindGroup,
+    // changed since the last upload
+    dirty: bool,
 }
 
 impl<T> UniformBinding<T> {
-    pub(super) fn uniform(&self) -> &T {
+    /// The uniform as it was last changed, not necessarily uploaded yet.
+    pub fn uniform(&self) -> &T {
         &self.uniform
     }
 
-    pub(super) fn uniform_mut(&mut self) -> &mut T {
+    /// Marks the uniform to be uploaded by the next [`Flush::flush`].
+    pub fn uniform_mut(&mut self) -> &mut T {
+        self.dirty = true;
         &mut self.uniform
     }
 
-    pub(super) fn buffer(&self) -> &Buffer {
+    /// Buffer the uniform is uploaded to.
+    pub fn buffer(&self) -> &Buffer {
         &self.buffer
     }
 
-    pub(super) fn bind_group(&self) -> &BindGroup {
+    /// Layout of [`bind_group`](Self::bind_group).
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Bind group with the uniform alone.
+    pub fn bind_group(&self) -> &BindGroup {
         &self.bind_group
     }
 }
 
-pub(super) trait Uniform {
-    fn get_buffer_label(&self) -> &'static str;
-
-    fn get_bind_group_label(&self) -> &'static str;
-
-    fn make_binding(self, device: &Device, bind_group_layout: &BindGroupLayout) -> UniformBinding<Self> where Self: Sized + Pod {
+impl<T: Uniform + Pod> UniformBinding<T> {
+    /// Uploads `uniform` and puts it in a bind group of its own, with the layout it describes.
+    pub(super) fn new(device: &Device, cache: &mut PipelineCache, uniform: T) -> Self {
+        let bind_group_layout = cache.bind_group_layout(device, &BindGroupLayoutDescriptor {
+            label: Some(uniform.get_layout_label()),
+            entries: &[uniform.get_layout_entry()],
+        });
         let buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some(self.get_buffer_label()),
-            contents: bytemuck::cast_slice(&[self]),
+            label: Some(uniform.get_buffer_label()),
+            contents: bytemuck::bytes_of(&uniform),
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
         let bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some(self.get_bind_group_label()),
-            layout: bind_group_layout,
+            label: Some(uniform.get_bind_group_label()),
+            layout: &bind_group_layout,
             entries: &[BindGroupEntry {
-                binding: 0,
+                binding: uniform.get_binding(),
                 resource: buffer.as_entire_binding(),
             }],
         });
 
-        UniformBinding::<Self> {
-            uniform: self,
+        Self {
+            uniform,
             buffer,
+            bind_group_layout,
             bind_group,
+            dirty: false,
         }
     }
 }
 
+/// Uniforms kept on the CPU and uploaded only once they changed.
+pub trait Flush {
+    /// Writes pending changes to the GPU, returns whether anything was written.
+    fn flush(&mut self, queue: &Queue) -> bool;
+}
+
+impl<T: Pod> Flush for UniformBinding<T> {
+    fn flush(&mut self, queue: &Queue) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.uniform));
+        self.dirty = false;
+        true
+    }
+}
+
+/// Flushes every uniform in `uniforms`, returns how many were written.
+pub(super) fn flush_all(queue: &Queue, uniforms: &mut [&mut dyn Flush]) -> usize {
+    uniforms.iter_mut().map(|uniform| uniform.flush(queue)).filter(|written| *written).count()
+}
+
+/// A `#[repr(C)]` struct shaders read as a `var<uniform>`, describing how it's bound.
+pub trait Uniform {
+    /// Label of the buffer holding the uniform.
+    fn get_buffer_label(&self) -> &'static str;
+
+    /// Label of the uniform's own bind group.
+    fn get_bind_group_label(&self) -> &'static str;
+
+    /// Label of the uniform's own bind group layout.
+    fn get_layout_label(&self) -> &'static str;
+
+    /// Stages reading the uniform.
+    fn get_visibility(&self) -> ShaderStages {
+        ShaderStages::VERTEX_FRAGMENT
+    }
+
+    /// `@binding` of the uniform when it has a bind group of its own.
+    fn get_binding(&self) -> u32 {
+        0
+    }
+
+    /// Layout entry binding the uniform at [`get_binding`](Self::get_binding).
+    fn get_layout_entry(&self) -> BindGroupLayoutEntry where Self: Sized {
+        BindGroupLayoutEntry {
+            binding: self.get_binding(),
+            visibility: self.get_visibility(),
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: NonZeroU64::new(std::mem::size_of::<Self>() as u64),
+            },
+            count: None,
+        }
+    }
+
+}
+
 impl Uniform for system::SystemUniform {
     fn get_buffer_label(&self) -> &'static str {
         "System Buffer"
@@ -69,6 +152,15 @@ impl Uniform for system::SystemUniform {
     fn get_bind_group_label(&self) -> &'static str {
         "System Bind Group"
     }
+
+    fn get_layout_label(&self) -> &'static str {
+        "System Bind Group Layout"
+    }
+
+    // compute passes read the time and frame too
+    fn get_visibility(&self) -> ShaderStages {
+        ShaderStages::VERTEX_FRAGMENT | ShaderStages::COMPUTE
+    }
 }
 
 impl Uniform for camera::CameraUniform {
@@ -79,4 +171,44 @@ impl Uniform for camera::CameraUniform {
     fn get_bind_group_label(&self) -> &'static str {
         "Camera Bind Group"
     }
+
+    fn get_layout_label(&self) -> &'static str {
+        "Camera Bind Group Layout"
+    }
+
+    fn get_visibility(&self) -> ShaderStages {
+        ShaderStages::VERTEX_FRAGMENT | ShaderStages::COMPUTE
+    }
+}
+
+impl Uniform for compare::CompareUniform {
+    fn get_buffer_label(&self) -> &'static str {
+        "Compare Buffer"
+    }
+
+    fn get_bind_group_label(&self) -> &'static str {
+        "Compare Bind Group"
+    }
+
+    fn get_layout_label(&self) -> &'static str {
+        "Compare Bind Group Layout"
+    }
+
+    fn get_visibility(&self) -> ShaderStages {
+        ShaderStages::FRAGMENT
+    }
+}
+
+impl Uniform for light::LightUniform {
+    fn get_buffer_label(&self) -> &'static str {
+        "Light Buffer"
+    }
+
+    fn get_bind_group_label(&self) -> &'static str {
+        "Light Bind Group"
+    }
+
+    fn get_layout_label(&self) -> &'static str {
+        "Light Bind Group Layout"
+    }
 }
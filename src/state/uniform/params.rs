@@ -0,0 +1,97 @@
+use std::fmt::Write;
+use anyhow::{bail, Result};
+
+// a field of `Params`, offset and length in floats
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    name: String,
+    offset: usize,
+    len: usize,
+}
+
+/// Named values of one to four floats, laid out like the WGSL uniform struct `Params`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(in crate::state) struct Params {
+    fields: Vec<Field>,
+    data: Vec<f32>,
+}
+
+impl Params {
+    /// Fields are declared in the order of `values`.
+    pub(in crate::state) fn new<'a>(values: impl IntoIterator<Item = (&'a str, &'a [f32])>) -> Result<Self> {
+        let mut fields: Vec<Field> = Vec::new();
+        let mut data = Vec::new();
+        for (name, value) in values {
+            if !is_identifier(name) {
+                bail!("`{}` isn't a valid WGSL identifier", name);
+            }
+            if fields.iter().any(|field| field.name == name) {
+                bail!("`{}` is declared twice", name);
+            }
+            // scalars align to 4 bytes, vec2 to 8, vec3 and vec4 to 16
+            let len = value.len();
+            let align = match len {
+                1 => 1,
+                2 => 2,
+                3 | 4 => 4,
+                _ => bail!("`{}` has {} components, expected 1 to 4", name, len),
+            };
+            let offset = data.len().div_ceil(align) * align;
+            data.resize(offset, 0.0);
+            data.extend_from_slice(value);
+            fields.push(Field { name: name.to_string(), offset, len });
+        }
+        // uniform structs are a multiple of 16 bytes
+        data.resize(data.len().div_ceil(4) * 4, 0.0);
+        Ok(Self { fields, data })
+    }
+
+    pub(in crate::state) fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub(in crate::state) fn get(&self, name: &str) -> Option<&[f32]> {
+        let field = self.fields.iter().find(|field| field.name == name)?;
+        Some(&self.data[field.offset..field.offset + field.len])
+    }
+
+    /// Overwrites `name`, which must keep its number of components.
+    pub(in crate::state) fn set(&mut self, name: &str, value: &[f32]) -> Result<()> {
+        let Some(field) = self.fields.iter().find(|field| field.name == name) else {
+            bail!("unknown parameter `{}`, available: {}", name,
+                  self.fields.iter().map(|field| field.name.as_str()).collect::<Vec<_>>().join(", "));
+        };
+        if value.len() != field.len {
+            bail!("parameter `{}` has {} components, not {}", name, field.len, value.len());
+        }
+        self.data[field.offset..field.offset + field.len].copy_from_slice(value);
+        Ok(())
+    }
+
+    pub(in crate::state) fn bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.data)
+    }
+
+    /// Declaration of the `Params` struct, empty without parameters.
+    pub(in crate::state) fn wgsl(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+        let mut wgsl = String::from("struct Params {\n");
+        for field in &self.fields {
+            let ty = match field.len {
+                1 => "f32".to_string(),
+                len => format!("vec{}<f32>", len),
+            };
+            let _ = writeln!(wgsl, "    {}: {},", field.name, ty);
+        }
+        wgsl.push_str("}\n");
+        wgsl
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -1,29 +1,58 @@
 use bytemuck::{Pod, Zeroable};
-use winit::dpi::PhysicalSize;
-use crate::state::time::Instant;
+use wgsl_layout::wgsl_layout;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 
+#[wgsl_layout(name = "System")]
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
 pub(in crate::state) struct SystemUniform {
     time: f32,
-    _padding_01_: [u32; 3],
+    frame: u32,
     resolution: [u32; 2],
-    _padding_02_: [u32; 2],
+    mouse: [f32; 4],
 }
 
 impl SystemUniform {
-    pub(in crate::state) fn new(resolution: PhysicalSize<u32>, start_time: Instant) -> Self {
+    pub(in crate::state) fn new(resolution: PhysicalSize<u32>) -> Self {
         Self {
-            time: start_time.elapsed().as_secs_f32(),
-            _padding_01_: [0; 3],
+            time: 0.0,
+            frame: 0,
             resolution: [resolution.width, resolution.height],
-            _padding_02_: [0; 2],
+            mouse: [0.0; 4],
         }
     }
 
-    pub(in crate::state) fn update_system(&mut self, resolution: PhysicalSize<u32>, start_time: Instant) {
-        // update time to number of milliseconds since program start
-        self.time = start_time.elapsed().as_secs_f32();
+    /// `time` is in seconds since the scene started.
+    pub(in crate::state) fn update_system(&mut self, resolution: PhysicalSize<u32>, time: f32, frame: u32) {
+        self.time = time;
+        self.frame = frame;
         self.resolution = [resolution.width, resolution.height];
     }
+
+    /// The same frame on a target `scale` times as large, with the cursor where it would be on it.
+    pub(in crate::state) fn scaled(&self, scale: u32) -> Self {
+        Self {
+            resolution: self.resolution.map(|length| length * scale),
+            mouse: self.mouse.map(|coordinate| coordinate * scale as f32),
+            ..*self
+        }
+    }
+
+    /// Tracks the cursor like Shadertoy's `iMouse`: `xy` is the position while the button is held,
+    /// `zw` the position of the last click, negated once the button is released. Origin is bottom left.
+    pub(in crate::state) fn update_mouse(&mut self, position: PhysicalPosition<f64>, pressed: bool, resolution: PhysicalSize<u32>) {
+        let x = position.x as f32;
+        let y = resolution.height as f32 - position.y as f32;
+        if pressed {
+            if self.mouse[2] <= 0.0 {
+                self.mouse[2] = x;
+                self.mouse[3] = y;
+            }
+            self.mouse[0] = x;
+            self.mouse[1] = y;
+        } else {
+            self.mouse[2] = -self.mouse[2].abs();
+            self.mouse[3] = -self.mouse[3].abs();
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,228 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    error::OsError,
+    event_loop::EventLoopWindowTarget,
+    monitor::MonitorHandle,
+    window::{Fullscreen, Window, WindowBuilder}
+};
+use super::{pipeline::cache::CacheStats, time::{Duration, Instant}};
+
+/// What fullscreen means for a window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// A borderless window covering the monitor.
+    #[default]
+    Borderless,
+    /// The monitor's largest video mode, at its highest refresh rate.
+    Exclusive,
+}
+
+/// How [`run_with`](crate::run_with) opens its window.
+#[derive(Debug, Clone)]
+pub struct WindowOptions {
+    /// Start of the title, followed by the shader name, frame rate and pipeline cache use.
+    pub title: String,
+    /// Inner size in physical pixels, the platform picks one otherwise.
+    pub size: Option<(u32, u32)>,
+    /// Start fullscreen. F11 toggles it either way.
+    pub fullscreen: bool,
+    /// Whether fullscreen is borderless or changes the video mode.
+    pub fullscreen_mode: FullscreenMode,
+    /// Index into the available monitors, the window is centered on it.
+    pub monitor: Option<usize>,
+    /// Where the window's position, size and fullscreen state are kept between runs. The options
+    /// above win over the ones restored from it.
+    pub geometry_file: Option<PathBuf>,
+    /// Screenshots taken with F2 are this many times the window size.
+    pub screenshot_scale: u32,
+    /// Where F2 saves screenshots natively, browsers download them instead.
+    pub screenshot_dir: PathBuf,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            title: env!("CARGO_PKG_NAME").to_string(),
+            size: None,
+            fullscreen: false,
+            fullscreen_mode: FullscreenMode::default(),
+            monitor: None,
+            geometry_file: None,
+            screenshot_scale: 1,
+            screenshot_dir: PathBuf::from("."),
+        }
+    }
+}
+
+// where the window was when it wasn't fullscreen
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct WindowGeometry {
+    position: Option<(i32, i32)>,
+    size: Option<(u32, u32)>,
+    fullscreen: bool,
+    monitor: Option<String>,
+}
+
+impl WindowGeometry {
+    // a missing file is the first run, anything else is worth a warning
+    fn load(path: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        ron::from_str(&text).map_err(|error| log::warn!("Ignoring `{}`: {}", path.display(), error)).ok()
+    }
+
+    fn save(&self, path: &Path) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|text| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+                }
+                std::fs::write(path, text).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            log::warn!("Couldn't save the window geometry to `{}`: {}", path.display(), error);
+        }
+    }
+}
+
+/// Keeps the title, fullscreen state and remembered geometry of the window [`run_with`](crate::run_with) opens.
+pub(crate) struct WindowChrome {
+    title: String,
+    fullscreen_mode: FullscreenMode,
+    geometry_file: Option<PathBuf>,
+    geometry: WindowGeometry,
+    shader: String,
+    frames: u32,
+    fps: f64,
+    since: Instant,
+}
+
+impl WindowChrome {
+    pub(crate) fn build<T>(options: &WindowOptions, event_loop: &EventLoopWindowTarget<T>) -> Result<(Window, Self), OsError> {
+        let saved = options.geometry_file.as_deref().and_then(WindowGeometry::load).unwrap_or_default();
+        let chosen = options.monitor.and_then(|index| {
+            let monitor = event_loop.available_monitors().nth(index);
+            if monitor.is_none() {
+                let names: Vec<_> = event_loop.available_monitors().enumerate()
+                    .map(|(index, monitor)| format!("{} ({})", index, monitor.name().unwrap_or_default()))
+                    .collect();
+                log::warn!("There's no monitor {}, available: {}", index, names.join(", "));
+            }
+            monitor
+        });
+        let monitor = chosen.clone().or_else(|| {
+            let name = saved.monitor.as_ref()?;
+            event_loop.available_monitors().find(|monitor| monitor.name().as_ref() == Some(name))
+        });
+
+        let mut builder = WindowBuilder::new().with_title(&options.title);
+        let size = options.size.or(saved.size);
+        if let Some((width, height)) = size {
+            builder = builder.with_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some(monitor) = &chosen {
+            let (width, height) = size.unwrap_or((monitor.size().width / 2, monitor.size().height / 2));
+            let offset = |available: u32, used: u32| (available.saturating_sub(used) / 2) as i32;
+            let position = monitor.position();
+            builder = builder.with_position(PhysicalPosition::new(position.x + offset(monitor.size().width, width),
+                                                                  position.y + offset(monitor.size().height, height)));
+        } else if let Some((x, y)) = saved.position {
+            // monitors may have been unplugged since, the window shouldn't open out of sight
+            if event_loop.available_monitors().any(|monitor| contains(&monitor, x, y)) {
+                builder = builder.with_position(PhysicalPosition::new(x, y));
+            }
+        }
+        if options.fullscreen || saved.fullscreen {
+            let monitor = monitor.or_else(|| event_loop.primary_monitor());
+            builder = builder.with_fullscreen(fullscreen(options.fullscreen_mode, monitor));
+        }
+
+        let window = builder.build(event_loop)?;
+        let chrome = Self {
+            title: options.title.clone(),
+            fullscreen_mode: options.fullscreen_mode,
+            geometry_file: options.geometry_file.clone(),
+            geometry: WindowGeometry { size, ..saved },
+            shader: String::new(),
+            frames: 0,
+            fps: 0.0,
+            since: Instant::now(),
+        };
+        Ok((window, chrome))
+    }
+
+    pub(crate) fn toggle_fullscreen(&self, window: &Window) {
+        if window.fullscreen().is_some() {
+            window.set_fullscreen(None);
+        } else {
+            window.set_fullscreen(fullscreen(self.fullscreen_mode, window.current_monitor()));
+        }
+    }
+
+    /// Remembers where the window is, unless it's fullscreen.
+    pub(crate) fn moved(&mut self, window: &Window, position: PhysicalPosition<i32>) {
+        if window.fullscreen().is_none() {
+            self.geometry.position = Some((position.x, position.y));
+        }
+    }
+
+    pub(crate) fn resized(&mut self, window: &Window, size: PhysicalSize<u32>) {
+        if window.fullscreen().is_none() && size.width > 0 && size.height > 0 {
+            self.geometry.size = Some((size.width, size.height));
+        }
+    }
+
+    /// Counts a frame, refreshing the title every second and whenever the shader changes.
+    pub(crate) fn frame(&mut self, window: &Window, shader: &str, pipelines: CacheStats) {
+        self.frames += 1;
+        let elapsed = self.since.elapsed();
+        let second = elapsed >= Duration::from_secs(1);
+        if second {
+            self.fps = self.frames as f64 / elapsed.as_secs_f64();
+            self.frames = 0;
+            self.since = Instant::now();
+        }
+        if second || shader != self.shader {
+            self.shader = shader.to_string();
+            // nothing has been measured during the first second
+            let cache = format!("{} pipelines, {} reused", pipelines.pipelines(), pipelines.hits);
+            let title = match self.fps {
+                fps if fps > 0.0 => format!("{} - {} - {:.0} fps - {}", self.title, self.shader, fps, cache),
+                _ => format!("{} - {} - {}", self.title, self.shader, cache),
+            };
+            window.set_title(&title);
+        }
+    }
+
+    /// Writes the geometry to the file it was restored from.
+    pub(crate) fn save(&mut self, window: &Window) {
+        let Some(path) = &self.geometry_file else {
+            return;
+        };
+        self.geometry.fullscreen = window.fullscreen().is_some();
+        self.geometry.monitor = window.current_monitor().and_then(|monitor| monitor.name());
+        self.geometry.save(path);
+    }
+}
+
+fn contains(monitor: &MonitorHandle, x: i32, y: i32) -> bool {
+    let (position, size) = (monitor.position(), monitor.size());
+    (position.x..position.x + size.width as i32).contains(&x) && (position.y..position.y + size.height as i32).contains(&y)
+}
+
+// exclusive fullscreen needs a video mode, without one the window goes borderless
+fn fullscreen(mode: FullscreenMode, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    if mode == FullscreenMode::Exclusive {
+        let video_mode = monitor.as_ref().and_then(|monitor| monitor.video_modes().max_by_key(|mode| {
+            (mode.size().width * mode.size().height, mode.refresh_rate_millihertz(), mode.bit_depth())
+        }));
+        match video_mode {
+            Some(video_mode) => return Some(Fullscreen::Exclusive(video_mode)),
+            None => log::warn!("No video mode to go exclusive fullscreen with, going borderless"),
+        }
+    }
+    Some(Fullscreen::Borderless(monitor))
+}
@@ -0,0 +1,142 @@
+//! Renders bundled shaders without a window on a software adapter and compares the frames with the
+//! reference PNGs in `tests/golden`.
+//!
+//! Frames that stray too far are written to `target/tmp/golden` with an image marking the pixels that
+//! differ. `GOLDEN=overwrite cargo test --test golden` replaces the references with the current frames.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration
+};
+use hello_world::{wgpu::Backends, Renderer, RendererConfig};
+use image::{Rgba, RgbaImage};
+
+const SIZE: (u32, u32) = (128, 72);
+
+// devices are created one at a time, GL drivers don't all cope with several threads doing it
+static GPU: Mutex<()> = Mutex::new(());
+
+/// How far a frame may stray from its reference.
+#[derive(Debug, Clone, Copy)]
+struct Tolerance {
+    /// Largest channel difference of a pixel still counted as equal.
+    threshold: u8,
+    /// Fraction of pixels allowed to differ by more.
+    differing: f64,
+}
+
+// smooth shaders only differ by rounding
+const SMOOTH: Tolerance = Tolerance { threshold: 2, differing: 0.0 };
+// noise hashes the bits of interpolated floats, so a few pixels may change with the rasterizer
+const NOISE: Tolerance = Tolerance { threshold: 2, differing: 0.01 };
+
+#[derive(Debug)]
+struct Comparison {
+    differing: usize,
+    largest: u8,
+    // the reference dimmed to gray, with differing pixels in red
+    diff: RgbaImage,
+}
+
+impl Comparison {
+    fn new(frame: &RgbaImage, reference: &RgbaImage, threshold: u8) -> Self {
+        assert_eq!(frame.dimensions(), reference.dimensions(), "the frame and its reference differ in size");
+        let mut differing = 0;
+        let mut largest = 0;
+        let diff = RgbaImage::from_fn(frame.width(), frame.height(), |x, y| {
+            let (actual, expected) = (frame.get_pixel(x, y), reference.get_pixel(x, y));
+            let difference = actual.0.iter().zip(expected.0).map(|(a, e)| a.abs_diff(e)).max().unwrap_or(0);
+            largest = largest.max(difference);
+            if difference > threshold {
+                differing += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                let gray = ((expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 12) as u8;
+                Rgba([gray, gray, gray, 255])
+            }
+        });
+        Self { differing, largest, diff }
+    }
+
+    fn fraction(&self) -> f64 {
+        self.differing as f64 / (self.diff.width() * self.diff.height()) as f64
+    }
+}
+
+fn render(shader: &str, time: f32) -> RgbaImage {
+    let _gpu = GPU.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let config = RendererConfig {
+        // llvmpipe, the same rasterizer locally as in CI
+        backends: Backends::GL,
+        force_fallback_adapter: true,
+        ..RendererConfig::default()
+    };
+    let builder = Renderer::builder().config(config).shader(shader).time(Duration::from_secs_f32(time));
+    let mut renderer = pollster::block_on(builder.build_headless(SIZE.0, SIZE.1))
+        .unwrap_or_else(|error| panic!("{}\nthe golden tests need Mesa's llvmpipe", error));
+    renderer.update(Duration::ZERO);
+    renderer.render();
+    pollster::block_on(renderer.read_pixels()).expect("the frame couldn't be read back")
+}
+
+fn output_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn check(name: &str, shader: &str, time: f32, tolerance: Tolerance) {
+    let frame = render(shader, time);
+    let reference_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.png", name));
+    if std::env::var_os("GOLDEN").is_some_and(|value| value == "overwrite") {
+        frame.save(&reference_path).unwrap();
+        return;
+    }
+
+    let frame_path = output_dir().join(format!("{}.png", name));
+    let Ok(reference) = image::open(&reference_path) else {
+        frame.save(&frame_path).unwrap();
+        panic!("there's no reference `{}`, the frame is in `{}`. Run with GOLDEN=overwrite to keep it",
+               reference_path.display(), frame_path.display());
+    };
+    let comparison = Comparison::new(&frame, &reference.to_rgba8(), tolerance.threshold);
+    if comparison.fraction() > tolerance.differing {
+        let diff_path = output_dir().join(format!("{}.diff.png", name));
+        frame.save(&frame_path).unwrap();
+        comparison.diff.save(&diff_path).unwrap();
+        panic!("{} of {} pixels differ from `{}` by up to {}, {:.2}% allowed. The frame is in `{}`, the difference in `{}`",
+               comparison.differing, SIZE.0 * SIZE.1, reference_path.display(), comparison.largest,
+               tolerance.differing * 100.0, frame_path.display(), diff_path.display());
+    }
+}
+
+#[test]
+fn default_at_start() {
+    check("default_0", "default", 0.0, SMOOTH);
+}
+
+#[test]
+fn default_later() {
+    check("default_2_5", "default", 2.5, SMOOTH);
+}
+
+#[test]
+fn hash_at_start() {
+    check("hash_0", "hash", 0.0, NOISE);
+}
+
+#[test]
+fn hash_later() {
+    check("hash_1", "hash", 1.0, NOISE);
+}
+
+#[test]
+fn comparison_catches_changes() {
+    let frame = render("default", 0.0);
+    assert_eq!(Comparison::new(&frame, &frame, 0).differing, 0);
+    let other = render("hash", 0.0);
+    let comparison = Comparison::new(&frame, &other, NOISE.threshold);
+    assert!(comparison.fraction() > NOISE.differing, "only {} pixels differ", comparison.differing);
+    assert_eq!(comparison.diff.dimensions(), frame.dimensions());
+}
@@ -0,0 +1,234 @@
+//! `#[wgsl_layout]` lays out a `#[repr(C)]` struct the way WGSL lays out the same struct in
+//! a uniform or storage buffer. Padding fields are inserted wherever WGSL alignment leaves a gap,
+//! and the matching WGSL declaration is generated as the associated constant `WGSL`.
+//!
+//! Supported field types are `f32`, `u32` and `i32`, arrays of two to four of them as vectors,
+//! and `[[f32; 2 | 4]; 2..=4]` as column-major matrices.
+//!
+//! ```ignore
+//! #[wgsl_layout(name = "System")]
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! struct SystemUniform {
+//!     time: f32,
+//!     // 12 bytes of padding are inserted here
+//!     mouse: [f32; 4],
+//! }
+//! ```
+//!
+//! Padding fields are private, so build values with `..Zeroable::zeroed()` or `Default`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Error, Expr, ExprLit, Field, Fields, ItemStruct, Lit, LitStr, Result, Token, Type,
+    ext::IdentExt, punctuated::Punctuated
+};
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    F32,
+    U32,
+    I32,
+}
+
+impl Scalar {
+    fn parse(ty: &Type) -> Option<Self> {
+        let Type::Path(path) = ty else {
+            return None;
+        };
+        if path.qself.is_some() {
+            return None;
+        }
+        let ident = path.path.get_ident()?;
+        match ident.to_string().as_str() {
+            "f32" => Some(Self::F32),
+            "u32" => Some(Self::U32),
+            "i32" => Some(Self::I32),
+            _ => None,
+        }
+    }
+
+    fn wgsl(self) -> &'static str {
+        match self {
+            Self::F32 => "f32",
+            Self::U32 => "u32",
+            Self::I32 => "i32",
+        }
+    }
+}
+
+// WGSL type of a field with its alignment and size in bytes
+#[derive(Debug)]
+struct WgslType {
+    name: String,
+    align: usize,
+    size: usize,
+}
+
+const SUPPORTED: &str = "expected f32, u32, i32, [T; 2..=4] for vectors or [[f32; 2 | 4]; 2..=4] for matrices";
+
+fn round_up(align: usize, offset: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+fn array_len(len: &Expr) -> Result<usize> {
+    match len {
+        Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) => int.base10_parse(),
+        _ => Err(Error::new_spanned(len, "array length must be an integer literal")),
+    }
+}
+
+// vectors of 2 are aligned to 8 bytes, vectors of 3 and 4 to 16
+fn vector_align(len: usize) -> usize {
+    if len == 2 { 8 } else { 16 }
+}
+
+fn wgsl_type(ty: &Type) -> Result<WgslType> {
+    if let Some(scalar) = Scalar::parse(ty) {
+        return Ok(WgslType { name: scalar.wgsl().to_string(), align: 4, size: 4 });
+    }
+
+    let Type::Array(array) = ty else {
+        return Err(Error::new_spanned(ty, format!("unsupported field type, {}", SUPPORTED)));
+    };
+    let len = array_len(&array.len)?;
+    if !(2..=4).contains(&len) {
+        return Err(Error::new_spanned(&array.len, "vectors and matrices have 2 to 4 components"));
+    }
+
+    if let Some(scalar) = Scalar::parse(&array.elem) {
+        return Ok(WgslType {
+            name: format!("vec{}<{}>", len, scalar.wgsl()),
+            align: vector_align(len),
+            size: 4 * len,
+        });
+    }
+
+    if let Type::Array(column) = &*array.elem {
+        if let Some(Scalar::F32) = Scalar::parse(&column.elem) {
+            let rows = array_len(&column.len)?;
+            return match rows {
+                // columns are vectors, so Rust and WGSL agree unless a column needs padding
+                2 | 4 => Ok(WgslType {
+                    name: format!("mat{}x{}<f32>", len, rows),
+                    align: vector_align(rows),
+                    size: len * rows * 4,
+                }),
+                3 => Err(Error::new_spanned(ty, format!(
+                    "mat{}x3<f32> columns are padded to 16 bytes in WGSL, use [[f32; 4]; {}] instead", len, len))),
+                _ => Err(Error::new_spanned(&column.len, "vectors and matrices have 2 to 4 components")),
+            };
+        }
+    }
+
+    Err(Error::new_spanned(ty, format!("unsupported field type, {}", SUPPORTED)))
+}
+
+fn check_repr(item: &ItemStruct) -> Result<()> {
+    let mut repr_c = false;
+    for attr in item.attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") {
+                repr_c = true;
+                Ok(())
+            } else {
+                Err(meta.error("`#[wgsl_layout]` only supports `#[repr(C)]`"))
+            }
+        })?;
+    }
+    if !repr_c {
+        return Err(Error::new_spanned(&item.ident, "`#[wgsl_layout]` needs `#[repr(C)]` to control the field order"));
+    }
+    Ok(())
+}
+
+fn expand(mut item: ItemStruct, name: Option<String>) -> Result<TokenStream2> {
+    check_repr(&item)?;
+    if !item.generics.params.is_empty() {
+        return Err(Error::new_spanned(&item.generics, "`#[wgsl_layout]` doesn't support generic structs"));
+    }
+    let Fields::Named(fields) = &mut item.fields else {
+        return Err(Error::new_spanned(&item.ident, "`#[wgsl_layout]` needs a struct with named fields"));
+    };
+    if fields.named.is_empty() {
+        return Err(Error::new_spanned(&item.ident, "WGSL structs need at least one field"));
+    }
+
+    let ident = &item.ident;
+    let wgsl_name = name.unwrap_or_else(|| ident.unraw().to_string());
+    let mut wgsl = format!("struct {} {{\n", wgsl_name);
+    let mut laid_out = Punctuated::<Field, Token![,]>::new();
+    let mut checks = Vec::new();
+    let mut align = 0;
+    let mut offset = 0;
+    let mut padding = 0usize;
+
+    let mut pad = |laid_out: &mut Punctuated<Field, Token![,]>, bytes: usize| {
+        padding += 1;
+        let field_ident = format_ident!("_padding_{:02}_", padding);
+        laid_out.push(syn::parse_quote!(#field_ident: [u8; #bytes]));
+    };
+
+    for field in std::mem::take(&mut fields.named) {
+        let ty = wgsl_type(&field.ty)?;
+        let field_ident = field.ident.clone().expect("named field");
+        let field_offset = round_up(ty.align, offset);
+        if field_offset > offset {
+            pad(&mut laid_out, field_offset - offset);
+        }
+        wgsl.push_str(&format!("    {}: {},\n", field_ident.unraw(), ty.name));
+        let message = LitStr::new(&format!("`{}::{}` isn't at its WGSL offset of {} bytes",
+                                           ident, field_ident.unraw(), field_offset), Span::call_site());
+        checks.push(quote! {
+            assert!(::core::mem::offset_of!(#ident, #field_ident) == #field_offset, #message);
+        });
+        laid_out.push(field);
+        align = align.max(ty.align);
+        offset = field_offset + ty.size;
+    }
+
+    // the struct is as large as its stride in a WGSL array
+    let size = round_up(align, offset);
+    if size > offset {
+        pad(&mut laid_out, size - offset);
+    }
+    wgsl.push_str("}\n");
+    fields.named = laid_out;
+
+    let vis = &item.vis;
+    let size_message = LitStr::new(&format!("`{}` isn't as large as its WGSL counterpart, {} bytes", ident, size),
+                                   Span::call_site());
+    Ok(quote! {
+        #item
+
+        impl #ident {
+            /// WGSL declaration of this struct, with the same field offsets and size.
+            #vis const WGSL: &'static str = #wgsl;
+        }
+
+        const _: () = {
+            #(#checks)*
+            assert!(::core::mem::size_of::<#ident>() == #size, #size_message);
+        };
+    })
+}
+
+/// Inserts the padding WGSL expects between the fields of a `#[repr(C)]` struct and generates
+/// its WGSL declaration as `WGSL`. `name = "..."` renames the WGSL struct.
+#[proc_macro_attribute]
+pub fn wgsl_layout(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            Err(meta.error("expected `name = \"...\"`"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+    let item = parse_macro_input!(item as ItemStruct);
+    expand(item, name).unwrap_or_else(Error::into_compile_error).into()
+}
@@ -0,0 +1,142 @@
+//! Compares the layouts `#[wgsl_layout]` produces with what naga computes for the generated WGSL.
+
+use std::mem::{offset_of, size_of};
+use bytemuck::{Pod, Zeroable};
+use naga::{
+    proc::Layouter, TypeInner,
+    valid::{Capabilities, ValidationFlags, Validator}
+};
+use wgsl_layout::wgsl_layout;
+
+#[derive(Debug, PartialEq)]
+struct Layout {
+    members: Vec<(String, u32)>,
+    size: u32,
+}
+
+// parses `wgsl` as the type of a uniform, so naga also checks the uniform address space rules
+fn naga_layout(wgsl: &str, name: &str) -> Layout {
+    let source = format!("{}@group(0) @binding(0)\nvar<uniform> value: {};\n", wgsl, name);
+    let module = naga::front::wgsl::parse_str(&source).unwrap_or_else(|error| panic!("{}", error.emit_to_string(&source)));
+    Validator::new(ValidationFlags::all(), Capabilities::empty()).validate(&module)
+        .unwrap_or_else(|error| panic!("{} isn't a valid uniform: {:?}", name, error));
+
+    let mut layouter = Layouter::default();
+    layouter.update(&module.types, &module.constants).unwrap();
+    let (handle, ty) = module.types.iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some(name))
+        .unwrap_or_else(|| panic!("no struct {} in\n{}", name, wgsl));
+    let TypeInner::Struct { members, .. } = &ty.inner else {
+        panic!("{} isn't a struct", name);
+    };
+    Layout {
+        members: members.iter().map(|member| (member.name.clone().unwrap(), member.offset)).collect(),
+        size: layouter[handle].size,
+    }
+}
+
+macro_rules! assert_layout {
+    ($ty:ty, $name:literal, [$($field:ident),*]) => {
+        assert_eq!(naga_layout(<$ty>::WGSL, $name), Layout {
+            members: vec![$((stringify!($field).to_string(), offset_of!($ty, $field) as u32)),*],
+            size: size_of::<$ty>() as u32,
+        });
+    };
+}
+
+#[wgsl_layout]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+struct Scalars {
+    a: f32,
+    b: u32,
+    c: i32,
+}
+
+#[wgsl_layout(name = "System")]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+struct SystemUniform {
+    time: f32,
+    frame: u32,
+    resolution: [u32; 2],
+    mouse: [f32; 4],
+}
+
+#[wgsl_layout]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+struct Gaps {
+    time: f32,
+    offset: [f32; 2],
+    position: [f32; 3],
+    color: [f32; 4],
+    index: u32,
+}
+
+#[wgsl_layout]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Matrices {
+    scale: f32,
+    view_proj: [[f32; 4]; 4],
+    rotation: [[f32; 2]; 2],
+    tilt: [[f32; 2]; 3],
+    shear: [[f32; 4]; 2],
+    last: i32,
+}
+
+#[wgsl_layout]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+struct Vectors {
+    a: [i32; 3],
+    b: [u32; 2],
+    c: f32,
+    d: [f32; 3],
+}
+
+#[test]
+fn scalars() {
+    assert_layout!(Scalars, "Scalars", [a, b, c]);
+    assert_eq!(size_of::<Scalars>(), 12);
+}
+
+#[test]
+fn system() {
+    assert_layout!(SystemUniform, "System", [time, frame, resolution, mouse]);
+    assert_eq!(size_of::<SystemUniform>(), 32);
+}
+
+#[test]
+fn gaps() {
+    assert_layout!(Gaps, "Gaps", [time, offset, position, color, index]);
+    assert_eq!(offset_of!(Gaps, offset), 8);
+    assert_eq!(offset_of!(Gaps, position), 16);
+    assert_eq!(size_of::<Gaps>(), 64);
+}
+
+#[test]
+fn matrices() {
+    assert_layout!(Matrices, "Matrices", [scale, view_proj, rotation, tilt, shear, last]);
+    assert_eq!(offset_of!(Matrices, view_proj), 16);
+}
+
+#[test]
+fn vectors() {
+    assert_layout!(Vectors, "Vectors", [a, b, c, d]);
+    assert_eq!(offset_of!(Vectors, b), 16);
+}
+
+#[test]
+fn wgsl_text() {
+    assert_eq!(SystemUniform::WGSL, "struct System {\n    time: f32,\n    frame: u32,\n    resolution: vec2<u32>,\n    mouse: vec4<f32>,\n}\n");
+}
+
+#[test]
+fn padding_is_zeroed() {
+    let gaps = Gaps { time: 1.0, index: 7, ..Zeroable::zeroed() };
+    let bytes = bytemuck::bytes_of(&gaps);
+    assert_eq!(&bytes[4..8], &[0; 4]);
+    assert_eq!(bytes[48..52], 7u32.to_ne_bytes());
+}
@@ -0,0 +1,9 @@
+use wgsl_layout::wgsl_layout;
+
+#[wgsl_layout]
+#[repr(C)]
+struct Uniform {
+    normal: [[f32; 3]; 3],
+}
+
+fn main() {}
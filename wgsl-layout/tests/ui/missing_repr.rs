@@ -0,0 +1,8 @@
+use wgsl_layout::wgsl_layout;
+
+#[wgsl_layout]
+struct Uniform {
+    time: f32,
+}
+
+fn main() {}
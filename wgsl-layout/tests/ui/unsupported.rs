@@ -0,0 +1,10 @@
+use wgsl_layout::wgsl_layout;
+
+#[wgsl_layout]
+#[repr(C)]
+struct Uniform {
+    enabled: bool,
+    color: [f64; 4],
+}
+
+fn main() {}